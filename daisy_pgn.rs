@@ -126,7 +126,7 @@ pub fn parse_rank(s: &str) -> Result<(&str, Option<Rank>), DParseError> {
         }
 }
 
-impl FromStr for PieceType {
+impl FromStr for Role {
     type Err = DParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -136,8 +136,7 @@ impl FromStr for PieceType {
             "R" => Ok(Self::Rook),
             "B" => Ok(Self::Bishop),
             "N" => Ok(Self::Knight),
-            "wP" => Ok(Self::PawnsWhite),
-            "bP" => Ok(Self::PawnsBlack),
+            "P" => Ok(Self::Pawn),
             _ => Err(DParseError::NoMatch {
                 action: "matching piece",
                 args: "",
@@ -155,8 +154,8 @@ impl FromStr for SANply {
 
         let (c1, piece) = if let Ok((c1, piece)) = c
             .clone()
-            .text_alt(&MAJOR_PIECETYPES)
-            .parse_selection::<PieceType>()
+            .text_alt(&MAJOR_ROLES)
+            .parse_selection::<Role>()
             .validate() {
                 (c1, Some(piece))
             } else {
@@ -165,8 +164,8 @@ impl FromStr for SANply {
         
         let (c1, piece) = if let Ok((c1, piece)) = c
             .clone()
-            .text_alt(&MAJOR_PIECETYPES)
-            .parse_selection::<PieceType>()
+            .text_alt(&MAJOR_ROLES)
+            .parse_selection::<Role>()
             .validate() {
                 (c1, Some(piece))
             } else {
@@ -186,7 +185,7 @@ pub fn parse_opt_comment_with_move_num(s: &str) -> Result<(&str, ()), DParseErro
 }
 
 pub fn parse_pgn_move(s: &str) -> Result<(&str, PGNmove), DParseError> {
-    let (c, _move_num, _comment, white_ply) = Cursor::from(s)
+    let (c, move_num, _comment, white_ply) = Cursor::from(s)
         .digits(1..)
         .parse_selection::<u8>()
         .text(".")
@@ -211,10 +210,9 @@ pub fn parse_pgn_move(s: &str) -> Result<(&str, PGNmove), DParseError> {
     Ok((
         c.str()?,
         PGNmove {
-            white_ply,
-            white_ply_annotation: None,
-            black_ply: Some(black_ply),
-            black_ply_annotation: None,
+            move_number: move_num as u32,
+            white_ply: Some(PGNply { san: white_ply, check: None, nags: Vec::new(), comments: Vec::new(), variations: Vec::new() }),
+            black_ply: Some(PGNply { san: black_ply, check: None, nags: Vec::new(), comments: Vec::new(), variations: Vec::new() }),
         },
     ))
 }
@@ -236,8 +234,8 @@ mod tests {
         let (c, pgn_move) = parse_pgn_move(san_move).unwrap();
 
         assert_eq!(c, "");
-        assert_eq!(pgn_move.white_ply, SANply::Castle(Castle::KingSide));
-        assert_eq!(pgn_move.black_ply, Some(SANply::Castle(Castle::KingSide)));
+        assert_eq!(pgn_move.white_ply.map(| ply | ply.san), Some(SANply::Castle(Castle::KingSide)));
+        assert_eq!(pgn_move.black_ply.map(| ply | ply.san), Some(SANply::Castle(Castle::KingSide)));
 
     }
 
@@ -1,6 +1,12 @@
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+use crate::error::SquareError;
+
+pub mod attacks;
+pub mod bitboard;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(usize)]
 pub enum File {
     A,
@@ -14,6 +20,38 @@ pub enum File {
 }
 
 pub const FILE_LABELS: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+pub const FILES: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+
+impl File {
+    pub fn new(index: usize) -> Option<File> {
+        FILES.get(index).copied()
+    }
+
+    pub fn from_char(c: char) -> Option<File> {
+        FILE_LABELS.iter().position(| label | *label == c).map(| index | FILES[index])
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn char(self) -> char {
+        FILE_LABELS[self as usize]
+    }
+
+    pub fn offset(self, delta: i32) -> Option<File> {
+        usize::try_from(self as i32 + delta).ok().and_then(File::new)
+    }
+
+    // The file mirrored across the middle of the board, a to h
+    pub fn flip(self) -> File {
+        FILES[7 - self as usize]
+    }
+
+    pub fn distance(self, other: File) -> usize {
+        (self as usize).abs_diff(other as usize)
+    }
+}
 
 impl fmt::Display for File {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -21,7 +59,7 @@ impl fmt::Display for File {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(usize)]
 pub enum Rank {
     R1,
@@ -35,6 +73,47 @@ pub enum Rank {
 }
 
 pub const RANK_LABELS: [char; 8] = ['1', '2', '3', '4', '5', '6', '7', '8'];
+pub const RANKS: [Rank; 8] = [Rank::R1, Rank::R2, Rank::R3, Rank::R4, Rank::R5, Rank::R6, Rank::R7, Rank::R8];
+
+impl Rank {
+    pub fn new(index: usize) -> Option<Rank> {
+        RANKS.get(index).copied()
+    }
+
+    pub fn from_char(c: char) -> Option<Rank> {
+        RANK_LABELS.iter().position(| label | *label == c).map(| index | RANKS[index])
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn char(self) -> char {
+        RANK_LABELS[self as usize]
+    }
+
+    pub fn offset(self, delta: i32) -> Option<Rank> {
+        usize::try_from(self as i32 + delta).ok().and_then(Rank::new)
+    }
+
+    // The rank mirrored across the middle of the board, 1 to 8
+    pub fn flip(self) -> Rank {
+        RANKS[7 - self as usize]
+    }
+
+    pub fn distance(self, other: Rank) -> usize {
+        (self as usize).abs_diff(other as usize)
+    }
+
+    // The rank as seen from the side of the colour given, so that a player's back rank is always
+    // the first
+    pub fn relative_to(self, color: Color) -> Rank {
+        match color {
+            Color::White => self,
+            Color::Black => self.flip(),
+        }
+    }
+}
 
 impl fmt::Display for Rank {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -42,11 +121,38 @@ impl fmt::Display for Rank {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(usize)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Color::White => write!(f, "white"),
+            Color::Black => write!(f, "black"),
+        }
+    }
+}
+
 pub const BOARD_SIZE: usize = 64;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+// A square of the board, by reference from a1 as 0 along each rank up to h8 as 63. It can only be
+// made from a valid file and rank or a reference that is on the board.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Square {
-    pub reference: usize,
+    reference: usize,
 }
 
 pub const SQUARE_NAMES_BY_REF: [&str; BOARD_SIZE] = [
@@ -60,6 +166,82 @@ pub const SQUARE_NAMES_BY_REF: [&str; BOARD_SIZE] = [
     "a8", "b8", "c8", "d8", "e8", "f8", "g8", "h8"
 ];
 
+impl Square {
+    pub const fn new(file: File, rank: Rank) -> Square {
+        Square { reference: rank as usize * 8 + file as usize }
+    }
+
+    pub fn from_index(index: usize) -> Option<Square> {
+        (index < BOARD_SIZE).then_some(Square { reference: index })
+    }
+
+    // Every square from a1 to h8, along each rank in turn
+    pub fn all() -> impl DoubleEndedIterator<Item = Square> + ExactSizeIterator {
+        (0..BOARD_SIZE).map(| reference | Square { reference })
+    }
+
+    pub const fn index(self) -> usize {
+        self.reference
+    }
+
+    pub fn file(self) -> File {
+        FILES[self.reference % 8]
+    }
+
+    pub fn rank(self) -> Rank {
+        RANKS[self.reference / 8]
+    }
+
+    // The square the given number of files and ranks away, if that is still on the board
+    pub fn offset(self, file_delta: i32, rank_delta: i32) -> Option<Square> {
+        Some(Square::new(self.file().offset(file_delta)?, self.rank().offset(rank_delta)?))
+    }
+
+    // The square mirrored across the middle of the board between the fourth and fifth ranks, a1 to a8
+    pub fn flip_vertical(self) -> Square {
+        Square::new(self.file(), self.rank().flip())
+    }
+
+    // The square mirrored across the middle of the board between the d and e files, a1 to h1
+    pub fn flip_horizontal(self) -> Square {
+        Square::new(self.file().flip(), self.rank())
+    }
+
+    // a1 is a dark square, h1 a light one
+    pub fn is_light(self) -> bool {
+        (self.file() as usize + self.rank() as usize) % 2 == 1
+    }
+
+    // The number of king moves between the squares
+    pub fn distance(self, other: Square) -> usize {
+        self.file().distance(other.file()).max(self.rank().distance(other.rank()))
+    }
+
+    // The number of rook moves of one square between the squares
+    pub fn manhattan_distance(self, other: Square) -> usize {
+        self.file().distance(other.file()) + self.rank().distance(other.rank())
+    }
+}
+
+impl TryFrom<usize> for Square {
+    type Error = SquareError;
+
+    fn try_from(index: usize) -> Result<Self, Self::Error> {
+        Square::from_index(index).ok_or(SquareError::OutOfRange(index))
+    }
+}
+
+impl FromStr for Square {
+    type Err = SquareError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let invalid = || SquareError::InvalidName(name.to_string());
+        let mut chars = name.chars();
+        let (Some(file), Some(rank), None) = (chars.next(), chars.next(), chars.next()) else { return Err(invalid()) };
+        Ok(Square::new(File::from_char(file).ok_or_else(invalid)?, Rank::from_char(rank).ok_or_else(invalid)?))
+    }
+}
+
 impl fmt::Display for Square {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", SQUARE_NAMES_BY_REF[self.reference])
@@ -67,28 +249,60 @@ impl fmt::Display for Square {
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
-#[repr(usize)] // Consider using u16 or u32
-pub enum PieceType {
-    King = 0,
-    Queen = 1,
-    Rook = 2,
-    Bishop = 3,
-    Knight = 4,
-    PawnsWhite = 5,
-    PawnsBlack = 6,
-    RookCQ = 7,
-    RookCK = 8,
-    KingCB = 9,
-    KingCQ = 10,
-    KingCK = 11,
-}
-
-pub const COUNT_PIECETYPES: usize = 12;
-pub const PIECE_NAMES_SHORT: [&str; COUNT_PIECETYPES] = ["K", "Q", "R", "B", "N", "P", "P", "R", "R", "K", "K", "K"];
-pub const MAJOR_PIECETYPES: [&str; 5] = ["K", "Q", "R", "B", "N"];
-
-impl fmt::Display for PieceType {
+#[repr(usize)]
+pub enum Role {
+    King,
+    Queen,
+    Rook,
+    Bishop,
+    Knight,
+    Pawn,
+}
+
+pub const COUNT_ROLES: usize = 6;
+pub const ROLE_NAMES_SHORT: [&str; COUNT_ROLES] = ["K", "Q", "R", "B", "N", "P"];
+pub const MAJOR_ROLES: [&str; 5] = ["K", "Q", "R", "B", "N"];
+
+impl fmt::Display for Role {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", PIECE_NAMES_SHORT[*self as usize])
+        write!(f, "{}", ROLE_NAMES_SHORT[*self as usize])
     }
-}
\ No newline at end of file
+}
+
+// A piece on the board, its role together with the side it belongs to
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Piece {
+    pub color: Color,
+    pub role: Role,
+}
+
+impl Piece {
+    pub fn new(color: Color, role: Role) -> Piece {
+        Piece { color, role }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn square_tests() {
+        let e4: Square = "e4".parse().unwrap();
+        assert_eq!((e4.file(), e4.rank(), e4.index()), (File::E, Rank::R4, 28));
+        assert_eq!(Square::try_from(28), Ok(e4));
+        assert_eq!(Square::try_from(64).unwrap_err().to_string(), "square reference 64 is off the board");
+        assert_eq!("i9".parse::<Square>().unwrap_err().to_string(), "'i9' is not a square");
+        assert!("e44".parse::<Square>().is_err());
+
+        assert_eq!(e4.offset(2, -1), Some(Square::new(File::G, Rank::R3)));
+        assert_eq!(e4.offset(4, 0), None);
+        assert_eq!((e4.flip_vertical().to_string(), e4.flip_horizontal().to_string()), ("e5".to_string(), "d4".to_string()));
+        assert!(e4.is_light() && !Square::new(File::A, Rank::R1).is_light());
+        assert_eq!((e4.distance(Square::new(File::A, Rank::R1)), e4.manhattan_distance(Square::new(File::A, Rank::R1))), (4, 7));
+        assert_eq!(Square::all().map(| square | square.to_string()).collect::<Vec<_>>(), SQUARE_NAMES_BY_REF);
+        assert_eq!(Rank::R2.relative_to(Color::Black), Rank::R7);
+    }
+
+}
@@ -0,0 +1,207 @@
+use std::sync::OnceLock;
+
+use super::*;
+use super::bitboard::*;
+
+const KNIGHT_STEPS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const WHITE_PAWN_CAPTURES: [(i32, i32); 2] = [(-1, 1), (1, 1)];
+const BLACK_PAWN_CAPTURES: [(i32, i32); 2] = [(-1, -1), (1, -1)];
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+// The squares a single step of each kind reaches from every square, worked out at compile time
+const fn step_attacks(steps: &[(i32, i32)]) -> [Bitboard; BOARD_SIZE] {
+    let mut attacks = [Bitboard::EMPTY; BOARD_SIZE];
+    let mut reference = 0;
+    while reference < BOARD_SIZE {
+        let (file, rank) = ((reference % 8) as i32, (reference / 8) as i32);
+        let mut index = 0;
+        while index < steps.len() {
+            let (to_file, to_rank) = (file + steps[index].0, rank + steps[index].1);
+            if to_file >= 0 && to_file < 8 && to_rank >= 0 && to_rank < 8 {
+                attacks[reference].0 |= 1 << (to_rank * 8 + to_file);
+            }
+            index += 1;
+        }
+        reference += 1;
+    }
+    attacks
+}
+
+// Magic factors for each square, found by trying sparse random numbers until one maps every
+// arrangement of blockers to an entry holding its attacks
+const ROOK_MAGICS: [u64; BOARD_SIZE] = [
+    0x1080004008801020, 0x0840092002C03000, 0x1900200010400900, 0x0880100008000480,
+    0x4200100420080200, 0x8100020100080400, 0x0200040110886200, 0x0200008040220411,
+    0x0404800084400220, 0x0000401000402000, 0x0086001081220440, 0x0408800800100280,
+    0x000A001201040820, 0x8848800200840080, 0x4001000100040200, 0x0442000102105084,
+    0x9080010020804100, 0x0040404000201009, 0x0000808010002009, 0x2200090021D00100,
+    0x0008008008040080, 0x0004004002010040, 0x0011040008015042, 0x00000A0001768104,
+    0x0000800080204009, 0x2010004140002001, 0x9800200280100080, 0x1000100080080080,
+    0x0442000A00049020, 0x2100040080020080, 0x0800120400900148, 0x0010040A00128541,
+    0x2800804000800030, 0x1010002000400041, 0x4000200011004100, 0x0610008410800800,
+    0x0400802402800800, 0xC100020080800400, 0x0002000802000401, 0x0182085882000401,
+    0x0220204000808000, 0x2860100040024022, 0x0001002004110040, 0x99101042000A0020,
+    0x0004080004008080, 0x0010040002008080, 0x2012004881020004, 0x8300842444820011,
+    0x0088403882010200, 0x0820400080210100, 0x0110910040A00300, 0x0801100280080480,
+    0x0242009008200600, 0x1002000489500200, 0x0040800200010080, 0x0091800041000080,
+    0x0000209300488001, 0x04C1002414824001, 0x020020000B001041, 0x7000100004200901,
+    0x8002002004100802, 0x30010002084C0007, 0x0888221800813004, 0x4000002840840112,
+];
+
+const BISHOP_MAGICS: [u64; BOARD_SIZE] = [
+    0xA010041108003100, 0x006082020A002900, 0x6810010619200000, 0x08281A0520000408,
+    0x0001104001000400, 0x0018901008048400, 0x00040A0210245280, 0x000200210808A402,
+    0x9140048410821200, 0x0800091010820041, 0x20504804832202C0, 0x0100091401081000,
+    0x8021011140000012, 0x0810020804450400, 0x208B0542109008A2, 0x0080084A08040204,
+    0x0040E2A80811244C, 0x2505022008008108, 0x0430220100420040, 0x010A040420220040,
+    0x1105000290400000, 0x0093001200822120, 0x4000A62048043004, 0x280120048A015004,
+    0x006090002A020814, 0x44042000240800D0, 0x01102800040A4400, 0x1004080080220040,
+    0x0001001011004024, 0x0010044000805040, 0x0914041200820100, 0x0004821012821480,
+    0x0024040500C05021, 0x0088611002080200, 0x0116080A00040020, 0x4000020080080080,
+    0x2450450140840040, 0x0000880201484100, 0x0222020404020092, 0x8081110600002E00,
+    0x2842101105000801, 0x1100809008001025, 0x00020202221C0400, 0x0422014022009020,
+    0x0210046102100C00, 0xC004008082029102, 0x00AA461801101200, 0x0404080080201108,
+    0x020542108C205002, 0x0410544804100100, 0x0040910841100000, 0x0400200042021100,
+    0x00004204850400C0, 0x0200100410A42102, 0x1040020801210102, 0x0805040410420000,
+    0x2884804130100200, 0x800C262201242000, 0x1058000194108800, 0x0014221054420204,
+    0x0104000012A02200, 0x0200881003300100, 0x0140400202840100, 0x0402020801010201,
+];
+
+static KNIGHT_ATTACKS: [Bitboard; BOARD_SIZE] = step_attacks(&KNIGHT_STEPS);
+static KING_ATTACKS: [Bitboard; BOARD_SIZE] = step_attacks(&KING_STEPS);
+static PAWN_ATTACKS: [[Bitboard; BOARD_SIZE]; 2] = [step_attacks(&WHITE_PAWN_CAPTURES), step_attacks(&BLACK_PAWN_CAPTURES)];
+
+pub fn knight_attacks(square: Square) -> Bitboard {
+    KNIGHT_ATTACKS[square.index()]
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+    KING_ATTACKS[square.index()]
+}
+
+// The squares a pawn of the colour given captures on from the square
+pub fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    PAWN_ATTACKS[color as usize][square.index()]
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = slider_tables();
+    tables.attacks[tables.rook[square.index()].index(occupied)]
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = slider_tables();
+    tables.attacks[tables.bishop[square.index()].index(occupied)]
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+// Walks each direction from the square up to and including the first occupied square
+fn sliding_attacks(square: Square, occupied: Bitboard, directions: &[(i32, i32)]) -> Bitboard {
+    let mut attacks = Bitboard::EMPTY;
+    for &(file_step, rank_step) in directions {
+        let mut current = square;
+        while let Some(to) = current.offset(file_step, rank_step) {
+            attacks = attacks.with(to);
+            current = to;
+            if occupied.contains(to) { break; }
+        }
+    }
+    attacks
+}
+
+// Multiplying the occupied squares that can block a slider by the magic factor gathers them into
+// the top bits, which index that square's part of the shared attack table
+#[derive(Debug, Clone, Copy, Default)]
+struct Magic {
+    mask: u64,
+    factor: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied.0 & self.mask).wrapping_mul(self.factor) >> self.shift) as usize
+    }
+}
+
+struct SliderTables {
+    rook: [Magic; BOARD_SIZE],
+    bishop: [Magic; BOARD_SIZE],
+    attacks: Vec<Bitboard>,
+}
+
+fn slider_tables() -> &'static SliderTables {
+    static TABLES: OnceLock<SliderTables> = OnceLock::new();
+    TABLES.get_or_init(SliderTables::new)
+}
+
+impl SliderTables {
+    fn new() -> SliderTables {
+        let mut attacks = Vec::new();
+        let rook = magics(&ROOK_MAGICS, &ROOK_DIRECTIONS, &mut attacks);
+        let bishop = magics(&BISHOP_MAGICS, &BISHOP_DIRECTIONS, &mut attacks);
+        SliderTables { rook, bishop, attacks }
+    }
+}
+
+// Fills each square's part of the attack table, indexed through its magic by every arrangement
+// of the pieces that could block it
+fn magics(factors: &[u64; BOARD_SIZE], directions: &[(i32, i32)], table: &mut Vec<Bitboard>) -> [Magic; BOARD_SIZE] {
+    let mut magics = [Magic::default(); BOARD_SIZE];
+    for (square, magic) in Square::all().zip(magics.iter_mut()) {
+        // Pieces on the edge of the board never block anything beyond them
+        let edges = ((Bitboard::rank(Rank::R1) | Bitboard::rank(Rank::R8)) & !Bitboard::rank(square.rank()))
+            | ((Bitboard::file(File::A) | Bitboard::file(File::H)) & !Bitboard::file(square.file()));
+        let mask = sliding_attacks(square, Bitboard::EMPTY, directions) & !edges;
+        *magic = Magic { mask: mask.0, factor: factors[square.index()], shift: 64 - mask.len() as u32, offset: table.len() };
+        table.resize(table.len() + (1 << mask.len()), Bitboard::EMPTY);
+
+        // Every subset of the mask, enumerated by the carry-rippler trick
+        let mut subset = Bitboard::EMPTY;
+        loop {
+            table[magic.index(subset)] = sliding_attacks(square, subset, directions);
+            subset = Bitboard(subset.0.wrapping_sub(mask.0) & mask.0);
+            if subset.is_empty() { break; }
+        }
+    }
+    magics
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn attack_tests() {
+        let square = | name: &str | name.parse::<Square>().unwrap();
+        let squares = | names: &[&str] | names.iter().map(| name | square(name)).collect::<Bitboard>();
+
+        assert_eq!(knight_attacks(square("a1")), squares(&["b3", "c2"]));
+        assert_eq!(king_attacks(square("h8")), squares(&["g8", "g7", "h7"]));
+        assert_eq!(pawn_attacks(Color::White, square("a2")), squares(&["b3"]));
+        assert_eq!(pawn_attacks(Color::Black, square("e5")), squares(&["d4", "f4"]));
+
+        let occupied = squares(&["d6", "f4", "b2", "g1"]);
+        assert_eq!(rook_attacks(square("d4"), occupied), squares(&["d5", "d6", "d3", "d2", "d1", "a4", "b4", "c4", "e4", "f4"]));
+        assert_eq!(bishop_attacks(square("d4"), occupied), squares(&["c5", "b6", "a7", "e5", "f6", "g7", "h8", "c3", "b2", "e3", "f2", "g1"]));
+
+        // The lookups agree with walking the rays for scattered arrangements of blockers
+        let mut seed = 1u64;
+        for _ in 0..200 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let occupied = Bitboard(seed & seed.rotate_left(17));
+            for square in Square::all() {
+                assert_eq!(rook_attacks(square, occupied), sliding_attacks(square, occupied, &ROOK_DIRECTIONS));
+                assert_eq!(bishop_attacks(square, occupied), sliding_attacks(square, occupied, &BISHOP_DIRECTIONS));
+            }
+        }
+    }
+
+}
@@ -0,0 +1,453 @@
+use std::fmt;
+
+use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError};
+
+use crate::definitions::*;
+use crate::pgn::PGNGameTerminationMarker;
+
+// nom error type used whilst parsing. Of the alternatives tried the error that got furthest
+// into the input is kept, along with the innermost context describing what was expected there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnParseError<'a> {
+    input: &'a str,
+    kind: ErrorKind,
+    expected: Option<&'static str>,
+    fen: Option<FenErrorKind>, // Set when a FEN tag failed to parse, the input is then where in the FEN
+}
+
+impl<'a> PgnParseError<'a> {
+    pub fn new(input: &'a str, expected: &'static str) -> Self {
+        PgnParseError { input, kind: ErrorKind::Fail, expected: Some(expected), fen: None }
+    }
+
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+}
+
+impl<'a> ParseError<&'a str> for PgnParseError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        PgnParseError { input, kind, expected: None, fen: None }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        if other.input.len() < self.input.len() || (other.input.len() == self.input.len() && other.expected.is_some()) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl<'a> ContextError<&'a str> for PgnParseError<'a> {
+    fn add_context(_input: &'a str, context: &'static str, mut other: Self) -> Self {
+        if other.expected.is_none() { other.expected = Some(context); }
+        other
+    }
+}
+
+impl<'a> FromExternalError<&'a str, FenError> for PgnParseError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, err: FenError) -> Self {
+        PgnParseError { input, kind, expected: None, fen: Some(err.kind) }
+    }
+}
+
+#[derive(Debug)]
+pub enum PgnErrorKind {
+    Syntax {
+        expected: String,
+        found: String,
+    },
+    Fen(FenErrorKind),
+    Uci(UciError),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for PgnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnErrorKind::Syntax { expected, found } if found.is_empty() => write!(f, "expected {}, found end of input", expected),
+            PgnErrorKind::Syntax { expected, found } => write!(f, "expected {}, found '{}'", expected, found),
+            PgnErrorKind::Fen(kind) => write!(f, "invalid FEN, {}", kind),
+            PgnErrorKind::Uci(err) => write!(f, "{}", err),
+            PgnErrorKind::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+// Crate level error, locating the problem by line and column (both counted from 1) and, when
+// reading a database, by the number of the game within it (also counted from 1)
+#[derive(Debug)]
+pub struct PgnError {
+    pub(crate) kind: PgnErrorKind,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) game: Option<usize>,
+}
+
+// Returns the byte offset of slice within source, slice must have been taken from source
+pub fn offset_in(source: &str, slice: &str) -> usize {
+    (slice.as_ptr() as usize).saturating_sub(source.as_ptr() as usize).min(source.len())
+}
+
+// Returns the line and column of the given byte offset into the source
+pub fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let preceding = &source[..offset];
+    let line = preceding.matches('\n').count() + 1;
+    let line_start = preceding.rfind('\n').map(| index | index + 1).unwrap_or(0);
+    (line, preceding[line_start..].chars().count() + 1)
+}
+
+impl PgnError {
+    // Converts a nom error into a PgnError located within source, the text that was parsed
+    pub fn from_parse_error(source: &str, err: nom::Err<PgnParseError<'_>>) -> PgnError {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) => {
+                // The error input is always a slice of the source, find where it starts
+                let offset = offset_in(source, err.input);
+                let found: String = err.input.split_whitespace().next().unwrap_or("").chars().take(20).collect();
+                let preceding = source[..offset].rsplit(char::is_whitespace).next().unwrap_or("");
+
+                let mut expected = err.expected.map(| expected | expected.to_string()).unwrap_or_else(|| format!("{:?}", err.kind));
+                if preceding.chars().any(char::is_alphanumeric) && offset < source.len() {
+                    expected = format!("{} after '{}'", expected, preceding);
+                }
+
+                let kind = match err.fen {
+                    Some(kind) => PgnErrorKind::Fen(kind),
+                    None => PgnErrorKind::Syntax { expected, found },
+                };
+                let (line, column) = line_and_column(source, offset);
+                PgnError { kind, line, column, game: None }
+            },
+            nom::Err::Incomplete(_) => {
+                let (line, column) = line_and_column(source, source.len());
+                PgnError { kind: PgnErrorKind::Syntax { expected: "more input".to_string(), found: String::new() }, line, column, game: None }
+            },
+        }
+    }
+
+    pub fn from_io_error(err: std::io::Error, line: usize) -> PgnError {
+        PgnError { kind: PgnErrorKind::Io(err), line, column: 1, game: None }
+    }
+
+    pub fn kind(&self) -> &PgnErrorKind {
+        &self.kind
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn game(&self) -> Option<usize> {
+        self.game
+    }
+
+    pub(crate) fn with_game(mut self, game: usize) -> Self {
+        self.game = Some(game);
+        self
+    }
+
+    // Moves the error down by the number of lines that preceded the text that was parsed
+    pub(crate) fn with_line_offset(mut self, lines: usize) -> Self {
+        self.line += lines;
+        self
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}:{}", self.kind, self.line, self.column)?;
+        if let Some(game) = self.game { write!(f, " in game {}", game)?; }
+        write!(f, "")
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenErrorKind {
+    MissingField(&'static str),
+    UnexpectedField,
+    RankCount,
+    RankLength(Rank),
+    InvalidPiece(char),
+    InvalidSideToMove,
+    InvalidCastlingRights,
+    CastlingWithoutRook,
+    InvalidEnPassant,
+    InvalidHalfmoveClock,
+    InvalidFullmoveNumber,
+    KingCount(Color),
+    PawnOnBackRank,
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenErrorKind::MissingField(field) => write!(f, "missing the {} field", field),
+            FenErrorKind::UnexpectedField => write!(f, "unexpected text after the fullmove number"),
+            FenErrorKind::RankCount => write!(f, "piece placement does not have eight ranks"),
+            FenErrorKind::RankLength(rank) => write!(f, "rank {} does not have eight squares", rank),
+            FenErrorKind::InvalidPiece(c) => write!(f, "'{}' is neither a piece nor a count of empty squares", c),
+            FenErrorKind::InvalidSideToMove => write!(f, "side to move is not 'w' or 'b'"),
+            FenErrorKind::InvalidCastlingRights => write!(f, "castling rights are not '-' or a combination of 'KQkq' or rook files"),
+            FenErrorKind::CastlingWithoutRook => write!(f, "castling right without a king and rook on the back rank"),
+            FenErrorKind::InvalidEnPassant => write!(f, "en passant square is not behind a pawn that has just moved two squares"),
+            FenErrorKind::InvalidHalfmoveClock => write!(f, "halfmove clock is not a number"),
+            FenErrorKind::InvalidFullmoveNumber => write!(f, "fullmove number is not a positive number"),
+            FenErrorKind::KingCount(color) => write!(f, "{} does not have exactly one king", color),
+            FenErrorKind::PawnOnBackRank => write!(f, "pawn on the first or eighth rank"),
+            FenErrorKind::OpponentInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+// Error from parsing FEN, the offset is in bytes from the start of the FEN to the problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct FenError {
+    pub(crate) kind: FenErrorKind,
+    pub(crate) offset: usize,
+}
+
+impl FenError {
+    pub fn new(kind: FenErrorKind, offset: usize) -> FenError {
+        FenError { kind, offset }
+    }
+
+    pub fn kind(&self) -> &FenErrorKind {
+        &self.kind
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at character {}", self.kind, self.offset + 1)
+    }
+}
+
+impl std::error::Error for FenError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SquareError {
+    InvalidName(String),
+    OutOfRange(usize),
+}
+
+impl fmt::Display for SquareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SquareError::InvalidName(name) => write!(f, "'{}' is not a square", name),
+            SquareError::OutOfRange(index) => write!(f, "square reference {} is off the board", index),
+        }
+    }
+}
+
+impl std::error::Error for SquareError {}
+
+// Error from resolving SAN against a position, holding the SAN as written
+#[derive(Debug, Clone, PartialEq)]
+pub enum SanError {
+    Syntax(String),
+    NoLegalMove(String),
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Syntax(san) => write!(f, "'{}' is not SAN", san),
+            SanError::NoLegalMove(san) => write!(f, "no legal move matches '{}'", san),
+            SanError::Ambiguous(san) => write!(f, "'{}' matches more than one legal move", san),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UciError {
+    Syntax(String),
+    NoLegalMove(String),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UciError::Syntax(uci) => write!(f, "'{}' is not a UCI move", uci),
+            UciError::NoLegalMove(uci) => write!(f, "no legal move matches '{}'", uci),
+        }
+    }
+}
+
+impl std::error::Error for UciError {}
+
+// Error from adding a tag pair to a game being built
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagError {
+    Reserved(String), // A tag the builder writes itself from its other settings
+}
+
+impl fmt::Display for TagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagError::Reserved(tag) => write!(f, "the {} tag is set by the game builder itself", tag),
+        }
+    }
+}
+
+impl std::error::Error for TagError {}
+
+// Error from annotating a game being built
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationError {
+    ClosingBrace(String), // A comment holding a }, which would end it early once exported
+    NoPly, // A NAG given before the first move, with no ply to annotate
+}
+
+impl fmt::Display for AnnotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnnotationError::ClosingBrace(comment) => write!(f, "comment '{}' contains '}}', which would end it when exported", comment),
+            AnnotationError::NoPly => write!(f, "no move has been played to annotate"),
+        }
+    }
+}
+
+impl std::error::Error for AnnotationError {}
+
+// Error from replaying a game, locating the ply that could not be played
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayError {
+    pub(crate) move_number: u32,
+    pub(crate) color: Color,
+    pub(crate) in_variation: bool,
+    pub(crate) error: SanError,
+}
+
+impl ReplayError {
+    pub fn move_number(&self) -> u32 {
+        self.move_number
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn in_variation(&self) -> bool {
+        self.in_variation
+    }
+
+    pub fn san_error(&self) -> &SanError {
+        &self.error
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at move {} for {}", self.error, self.move_number, self.color)?;
+        if self.in_variation { write!(f, " in a variation")?; }
+        write!(f, "")
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+// Disagreement between the Result tag, the game termination marker and the final position
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResultError {
+    TagMismatch {
+        tag: PGNGameTerminationMarker,
+        marker: PGNGameTerminationMarker,
+    },
+    PositionMismatch {
+        marker: PGNGameTerminationMarker,
+        adjudicated: PGNGameTerminationMarker,
+    },
+    Replay(ReplayError),
+}
+
+impl fmt::Display for ResultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResultError::TagMismatch { tag, marker } => write!(f, "Result tag \"{}\" does not match the game termination marker {}", tag, marker),
+            ResultError::PositionMismatch { marker, adjudicated } => write!(f, "game termination marker {} does not match the final position, which decides {}", marker, adjudicated),
+            ResultError::Replay(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ReplayError> for ResultError {
+    fn from(err: ReplayError) -> Self {
+        ResultError::Replay(err)
+    }
+}
+
+impl std::error::Error for ResultError {}
+
+// A problem found and recovered from whilst importing in lenient mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnDiagnostic {
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) message: String,
+}
+
+impl PgnDiagnostic {
+    // A diagnostic for the error, noting what was done to recover from it
+    pub fn from_error(err: &PgnError, recovery: &str) -> PgnDiagnostic {
+        PgnDiagnostic { line: err.line, column: err.column, message: format!("{}; {}", err.kind, recovery) }
+    }
+
+    // A diagnostic located at the start of position, which must be a slice of source
+    pub fn at(source: &str, position: &str, message: String) -> PgnDiagnostic {
+        let (line, column) = line_and_column(source, offset_in(source, position));
+        PgnDiagnostic { line, column, message }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for PgnDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}:{}", self.message, self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn line_and_column_test() {
+        let source = "[Event \"?\"]\n\n1. e4 Nz9";
+        assert_eq!(line_and_column(source, 0), (1, 1));
+        assert_eq!(line_and_column(source, source.find("Nz9").unwrap()), (3, 7));
+        assert_eq!(line_and_column(source, source.len()), (3, 10));
+    }
+
+}
@@ -1,4 +1,6 @@
 pub mod pgn;
 pub mod time_controls;
 pub mod definitions;
+pub mod position;
+pub mod error;
 pub mod daisy_pgn;
\ No newline at end of file
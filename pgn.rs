@@ -1,18 +1,52 @@
 pub mod pgn_import;
 pub mod pgn_export;
+pub mod pgn_database;
+pub mod pgn_replay;
+pub mod pgn_uci;
+pub mod pgn_result;
+pub mod pgn_builder;
 use crate::definitions::*;
 use crate::time_controls::*;
+use crate::error::*;
+use crate::position::*;
 
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CheckType {
     Check,
     CheckMate,
     StaleMate,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+// Numeric Annotation Glyph, written as $0 to $255. NAGs 1 to 6 are the numeric form of the
+// traditional move suffix annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Nag(pub u8);
+
+impl Nag {
+    pub const GOOD_MOVE: Nag = Nag(1);
+    pub const MISTAKE: Nag = Nag(2);
+    pub const BRILLIANT_MOVE: Nag = Nag(3);
+    pub const BLUNDER: Nag = Nag(4);
+    pub const SPECULATIVE_MOVE: Nag = Nag(5);
+    pub const DUBIOUS_MOVE: Nag = Nag(6);
+
+    pub fn from_suffix(suffix: &str) -> Option<Nag> {
+        MOVE_SUFFIX_ANNOTATIONS.iter().position(| s | *s == suffix).map(| nag | Nag(nag as u8 + 1))
+    }
+
+    pub fn suffix(&self) -> Option<&'static str> {
+        match self.0 {
+            1..=6 => Some(MOVE_SUFFIX_ANNOTATIONS[self.0 as usize - 1]),
+            _ => None,
+        }
+    }
+}
+
+pub const MOVE_SUFFIX_ANNOTATIONS: [&str; 6] = ["!", "?", "!!", "??", "!?", "?!"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Castle {
     KingSide,
     QueenSide,
@@ -21,71 +55,239 @@ pub enum Castle {
 #[derive(Debug, Clone, PartialEq)]
 // SAN = Standard Algebraic Notation for a Move
 pub struct SANPlyCoordinates {
-    from_file: Option<File>,
-    from_rank: Option<Rank>,
-    to_square: Square,
+    pub(crate) from_file: Option<File>,
+    pub(crate) from_rank: Option<Rank>,
+    pub(crate) to_square: Square,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SANply {
     Basic {
-        piece_moved: PieceType,
+        piece_moved: Role,
         mv: SANPlyCoordinates,
     },
     Capture {
-        piece_moved: PieceType,
+        piece_moved: Role,
         mv: SANPlyCoordinates,
     },
     Promotion {
         mv: SANPlyCoordinates,
-        piece_promoted: PieceType,
+        piece_promoted: Role,
     },
     CapturePromotion {
         mv: SANPlyCoordinates,
-        piece_promoted: PieceType,
+        piece_promoted: Role,
     },
     Castle(Castle),
 }
 
+impl SANPlyCoordinates {
+    // The file the piece moved from, if the SAN gives it
+    pub fn from_file(&self) -> Option<File> {
+        self.from_file
+    }
+
+    // The rank the piece moved from, if the SAN gives it
+    pub fn from_rank(&self) -> Option<Rank> {
+        self.from_rank
+    }
 
+    pub fn destination(&self) -> Square {
+        self.to_square
+    }
+}
 
+impl SANply {
+    // The role of the piece moving, pawns for promotions and the king when castling
+    pub fn piece_moved(&self) -> Role {
+        match self {
+            SANply::Basic { piece_moved, .. } | SANply::Capture { piece_moved, .. } => *piece_moved,
+            SANply::Promotion { .. } | SANply::CapturePromotion { .. } => Role::Pawn,
+            SANply::Castle(_) => Role::King,
+        }
+    }
 
-#[derive(Debug)]
+    pub fn piece_promoted(&self) -> Option<Role> {
+        match self {
+            SANply::Promotion { piece_promoted, .. } | SANply::CapturePromotion { piece_promoted, .. } => Some(*piece_promoted),
+            _ => None,
+        }
+    }
+
+    // Where the piece moves from and to, which castling does not give
+    pub fn coordinates(&self) -> Option<&SANPlyCoordinates> {
+        match self {
+            SANply::Basic { mv, .. } | SANply::Capture { mv, .. } | SANply::Promotion { mv, .. } | SANply::CapturePromotion { mv, .. } => Some(mv),
+            SANply::Castle(_) => None,
+        }
+    }
+
+    pub fn destination(&self) -> Option<Square> {
+        self.coordinates().map(SANPlyCoordinates::destination)
+    }
+
+    pub fn is_capture(&self) -> bool {
+        matches!(self, SANply::Capture { .. } | SANply::CapturePromotion { .. })
+    }
+
+    pub fn castle(&self) -> Option<Castle> {
+        match self {
+            SANply::Castle(castle) => Some(*castle),
+            _ => None,
+        }
+    }
+}
+
+// A single ply together with the comments that follow it and the recursive annotation
+// variations (RAV) that are alternatives to it, each variation being movetext in its own right
+#[derive(Debug, Clone)]
+pub struct PGNply {
+    pub(crate) san: SANply,
+    pub(crate) check: Option<CheckType>,
+    pub(crate) nags: Vec<Nag>,
+    pub(crate) comments: Vec<String>,
+    pub(crate) variations: Vec<PGNmovetext>,
+}
+
+impl PGNply {
+    pub fn san(&self) -> &SANply {
+        &self.san
+    }
+
+    // The square moved to, or None for castling
+    pub fn destination(&self) -> Option<Square> {
+        self.san.destination()
+    }
+
+    // The check or checkmate suffix as written after the move
+    pub fn check(&self) -> Option<CheckType> {
+        self.check
+    }
+
+    pub fn nags(&self) -> &[Nag] {
+        &self.nags
+    }
+
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    pub fn variations(&self) -> &[PGNmovetext] {
+        &self.variations
+    }
+
+    pub fn has_nag(&self, nag: Nag) -> bool {
+        self.nags.contains(&nag)
+    }
+
+    pub fn is_blunder(&self) -> bool {
+        self.has_nag(Nag::BLUNDER)
+    }
+}
+
+// White's ply is only absent when a game or variation starts with black's move, e.g. 23... Nf6
+#[derive(Debug, Clone)]
 pub struct PGNmove {
-    pub(crate) white_ply: SANply,
-    pub(crate) white_ply_annotation: Option<String>,
-    pub(crate) black_ply: Option<SANply>,
-    pub(crate) black_ply_annotation: Option<String>,
+    pub(crate) move_number: u32,
+    pub(crate) white_ply: Option<PGNply>,
+    pub(crate) black_ply: Option<PGNply>,
 }
 
-// Contains a ordered vector of moves
-#[derive(Debug)]
+impl PGNmove {
+    pub fn move_number(&self) -> u32 {
+        self.move_number
+    }
+
+    pub fn white_ply(&self) -> Option<&PGNply> {
+        self.white_ply.as_ref()
+    }
+
+    pub fn black_ply(&self) -> Option<&PGNply> {
+        self.black_ply.as_ref()
+    }
+}
+
+// Contains a ordered vector of moves, forming a game tree through the variations of each ply
+#[derive(Debug, Clone)]
 pub struct PGNmovetext {
-    moves: Vec<PGNmove>,
+    pub(crate) leading_comments: Vec<String>, // Comments before the first move
+    pub(crate) moves: Vec<PGNmove>,
 }
 
-#[derive(Debug)]
+impl PGNmovetext {
+    pub fn leading_comments(&self) -> &[String] {
+        &self.leading_comments
+    }
+
+    pub fn moves(&self) -> &[PGNmove] {
+        &self.moves
+    }
+
+    // Appends a ply, completing the last move with black's ply where it is for the same move
+    pub(crate) fn push_ply(&mut self, move_number: u32, color: Color, ply: PGNply) {
+        match (color, self.moves.last_mut()) {
+            (Color::Black, Some(last)) if last.move_number == move_number && last.black_ply.is_none() => last.black_ply = Some(ply),
+            (Color::White, _) => self.moves.push(PGNmove { move_number, white_ply: Some(ply), black_ply: None }),
+            (Color::Black, _) => self.moves.push(PGNmove { move_number, white_ply: None, black_ply: Some(ply) }),
+        }
+    }
+
+    pub(crate) fn last_ply_mut(&mut self) -> Option<&mut PGNply> {
+        self.moves.last_mut().and_then(| last | last.black_ply.as_mut().or(last.white_ply.as_mut()))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct PGNDateTag {
     year: Option<u16>,
     month: Option<u8>,
     day: Option<u8>,
 }
 
-#[derive(Debug)]
+// Parts of the date written as ?? are unknown
+impl PGNDateTag {
+    pub fn year(&self) -> Option<u16> {
+        self.year
+    }
+
+    pub fn month(&self) -> Option<u8> {
+        self.month
+    }
+
+    pub fn day(&self) -> Option<u8> {
+        self.day
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct PGNTimeTag {
     hour: Option<u8>,
     minute: Option<u8>,
     second: Option<u8>,
 }
 
-#[derive(Debug)]
+impl PGNTimeTag {
+    pub fn hour(&self) -> Option<u8> {
+        self.hour
+    }
+
+    pub fn minute(&self) -> Option<u8> {
+        self.minute
+    }
+
+    pub fn second(&self) -> Option<u8> {
+        self.second
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum PGNRoundTag {
     Unknown,
     NotApplicable,
     Name(String)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PGNGameTerminationMarker {
     WhiteWins,
     BlackWins,
@@ -93,13 +295,23 @@ pub enum PGNGameTerminationMarker {
     Undetermined,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PGNGenericTagPair {
     tag: String,
     value: String,
 }
 
-#[derive(Debug)]
+impl PGNGenericTagPair {
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct PGNTagPairRoster {
     event: Option<String>,
     site: Option<String>,
@@ -110,10 +322,80 @@ pub struct PGNTagPairRoster {
     result: PGNGameTerminationMarker,
     time: PGNTimeTag,
     time_control: TimeControlPeriod,
-    fen_string: Option<String>,
+    fen: Option<Position>, // Starting position given by the FEN tag, validated against SetUp
     other_tag_pairs: Vec<PGNGenericTagPair>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Standard,
+    Chess960,
+}
+
+// Names for Chess960 in use in the Variant tag, compared ignoring case, spaces and punctuation
+const CHESS960_VARIANT_NAMES: [&str; 5] = ["chess960", "960", "fischerandom", "fischerrandom", "frc"];
+
+impl PGNTagPairRoster {
+    pub fn event(&self) -> Option<&str> {
+        self.event.as_deref()
+    }
+
+    pub fn site(&self) -> Option<&str> {
+        self.site.as_deref()
+    }
+
+    pub fn date(&self) -> &PGNDateTag {
+        &self.date
+    }
+
+    pub fn round(&self) -> &PGNRoundTag {
+        &self.round
+    }
+
+    pub fn white(&self) -> Option<&str> {
+        self.white.as_deref()
+    }
+
+    pub fn black(&self) -> Option<&str> {
+        self.black.as_deref()
+    }
+
+    pub fn result(&self) -> PGNGameTerminationMarker {
+        self.result
+    }
+
+    pub fn time(&self) -> &PGNTimeTag {
+        &self.time
+    }
+
+    pub fn time_control(&self) -> &TimeControlPeriod {
+        &self.time_control
+    }
+
+    pub fn fen(&self) -> Option<&Position> {
+        self.fen.as_ref()
+    }
+
+    // Tag pairs other than the seven tag roster and the Time, TimeControl, SetUp and FEN tags, in
+    // the order given
+    pub fn other_tag_pairs(&self) -> &[PGNGenericTagPair] {
+        &self.other_tag_pairs
+    }
+
+    // The value of the first other tag pair with the tag given
+    pub fn other_tag(&self, tag: &str) -> Option<&str> {
+        self.other_tag_pairs.iter().find(| tag_pair | tag_pair.tag == tag).map(PGNGenericTagPair::value)
+    }
+
+    pub fn variant(&self) -> Variant {
+        let is_chess960 = self.other_tag_pairs.iter().filter(| tag_pair | tag_pair.tag == "Variant").any(| tag_pair | {
+            let name: String = tag_pair.value.chars().filter(char::is_ascii_alphanumeric).map(| c | c.to_ascii_lowercase()).collect();
+            CHESS960_VARIANT_NAMES.contains(&name.as_str())
+        });
+        if is_chess960 { Variant::Chess960 } else { Variant::Standard }
+    }
+}
+
 impl Default for PGNTagPairRoster {
     fn default() -> Self {
         PGNTagPairRoster {
@@ -126,7 +408,7 @@ impl Default for PGNTagPairRoster {
             result: PGNGameTerminationMarker::Undetermined,
             time: PGNTimeTag{ hour: None, minute: None, second: None },
             time_control: TimeControlPeriod::Unknown,
-            fen_string: None,
+            fen: None,
             other_tag_pairs: Vec::new(),
         }
     }
@@ -137,11 +419,69 @@ pub struct PGNFile {
     tag_pair_roster: PGNTagPairRoster,
     movetext: PGNmovetext,
     game_termination_marker: PGNGameTerminationMarker,
+    diagnostics: Vec<PgnDiagnostic>, // Problems recovered from when imported in lenient mode
+}
+
+impl PGNFile {
+    pub fn tags(&self) -> &PGNTagPairRoster {
+        &self.tag_pair_roster
+    }
+
+    pub fn movetext(&self) -> &PGNmovetext {
+        &self.movetext
+    }
+
+    // The moves of the mainline, each holding any variations on its plies
+    pub fn moves(&self) -> &[PGNmove] {
+        &self.movetext.moves
+    }
+
+    pub fn game_termination_marker(&self) -> PGNGameTerminationMarker {
+        self.game_termination_marker
+    }
+
+    pub fn diagnostics(&self) -> &[PgnDiagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.tag_pair_roster.variant()
+    }
+
+    // The position the game starts from, the standard starting position unless a FEN tag is given
+    pub fn starting_position(&self) -> Position {
+        let position = self.tag_pair_roster.fen.clone().unwrap_or_default();
+        match self.variant() {
+            Variant::Chess960 => position.into_chess960(),
+            Variant::Standard => position,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    // use super::*;
+    use super::*;
+    use crate::pgn::pgn_import::*;
+
+    #[test]
+    fn accessor_tests() {
+        let game = parse_pgn_game("[White \"Morphy\"] [Date \"1858.??.??\"] [Annotator \"Steinitz\"] [Result \"1-0\"] {Opera game} 1. e4 $1 {best} (1. d4 d5) e5 2. Nf3 Nf6 3. Bc4 Bc5 4. Nxe5 O-O 1-0").unwrap();
+        let tags = game.tags();
+        assert_eq!((tags.white(), tags.black(), tags.result()), (Some("Morphy"), None, PGNGameTerminationMarker::WhiteWins));
+        assert_eq!((tags.date().year(), tags.date().month()), (Some(1858), None));
+        assert_eq!(tags.other_tag("Annotator"), Some("Steinitz"));
+        assert_eq!(game.movetext().leading_comments(), ["Opera game"]);
+
+        let first = game.moves()[0].white_ply().unwrap();
+        assert_eq!((first.destination(), first.san().piece_moved(), first.nags()), (Some("e4".parse().unwrap()), Role::Pawn, &[Nag::GOOD_MOVE][..]));
+        assert_eq!((first.comments(), first.variations()[0].moves().len()), (&["best".to_string()][..], 1));
+
+        let last = &game.moves()[3];
+        assert_eq!((last.move_number(), last.white_ply().unwrap().san().is_capture()), (4, true));
+        assert_eq!((last.white_ply().unwrap().san().piece_moved(), last.white_ply().unwrap().san().coordinates().and_then(SANPlyCoordinates::from_file)), (Role::Knight, None));
+        assert_eq!(last.black_ply().unwrap().san().castle(), Some(Castle::KingSide));
+        assert_eq!(game.game_termination_marker(), PGNGameTerminationMarker::WhiteWins);
+    }
 
 }
\ No newline at end of file
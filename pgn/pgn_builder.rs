@@ -0,0 +1,207 @@
+use crate::error::*;
+use crate::position::*;
+use crate::position::movegen::*;
+use crate::time_controls::*;
+
+use super::*;
+use super::pgn_import::*;
+
+// Tag values are kept as written in PGN, as they are on import, with \ and " escaped
+fn escape_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Tags written from the builder's own settings, which tag() cannot add a second time
+const RESERVED_TAGS: [&str; 13] = ["Event", "Site", "Date", "Round", "White", "Black", "Result", "Time", "TimeControl", "SetUp", "Setup", "FEN", "Variant"];
+
+// Builds a game move by move, such as one being played on a server. Moves are checked for
+// legality as they are pushed and written as canonical SAN with the check suffix worked out
+// from the position, so the game exports as well formed PGN.
+#[derive(Debug, Clone)]
+pub struct GameBuilder {
+    tag_pair_roster: PGNTagPairRoster,
+    movetext: PGNmovetext,
+    game_termination_marker: PGNGameTerminationMarker,
+    position: Position,
+}
+
+impl Default for GameBuilder {
+    fn default() -> Self {
+        GameBuilder {
+            tag_pair_roster: PGNTagPairRoster::default(),
+            movetext: PGNmovetext { leading_comments: Vec::new(), moves: Vec::new() },
+            game_termination_marker: PGNGameTerminationMarker::Undetermined,
+            position: Position::default(),
+        }
+    }
+}
+
+impl GameBuilder {
+    pub fn new() -> Self {
+        GameBuilder::default()
+    }
+
+    // A game starting from the position given, recorded in the FEN tag. A Chess960 position is
+    // also recorded in the Variant tag.
+    pub fn from_position(position: Position) -> Self {
+        let mut builder = GameBuilder::default();
+        if position.is_chess960() {
+            builder.tag_pair_roster.other_tag_pairs.push(PGNGenericTagPair { tag: "Variant".to_string(), value: "Chess960".to_string() });
+        }
+        builder.tag_pair_roster.fen = Some(position.clone());
+        builder.position = position;
+        builder
+    }
+
+    // The position reached after the moves pushed so far
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn event(&mut self, event: &str) -> &mut Self {
+        self.tag_pair_roster.event = Some(escape_tag_value(event));
+        self
+    }
+
+    pub fn site(&mut self, site: &str) -> &mut Self {
+        self.tag_pair_roster.site = Some(escape_tag_value(site));
+        self
+    }
+
+    // Parts of the date left as None are written as ??
+    pub fn date(&mut self, year: Option<u16>, month: Option<u8>, day: Option<u8>) -> &mut Self {
+        self.tag_pair_roster.date = PGNDateTag { year, month, day };
+        self
+    }
+
+    pub fn round(&mut self, round: PGNRoundTag) -> &mut Self {
+        self.tag_pair_roster.round = match round {
+            PGNRoundTag::Name(name) => PGNRoundTag::Name(escape_tag_value(&name)),
+            round => round,
+        };
+        self
+    }
+
+    pub fn white(&mut self, white: &str) -> &mut Self {
+        self.tag_pair_roster.white = Some(escape_tag_value(white));
+        self
+    }
+
+    pub fn black(&mut self, black: &str) -> &mut Self {
+        self.tag_pair_roster.black = Some(escape_tag_value(black));
+        self
+    }
+
+    pub fn time(&mut self, hour: Option<u8>, minute: Option<u8>, second: Option<u8>) -> &mut Self {
+        self.tag_pair_roster.time = PGNTimeTag { hour, minute, second };
+        self
+    }
+
+    pub fn time_control(&mut self, time_control: TimeControlPeriod) -> &mut Self {
+        self.tag_pair_roster.time_control = time_control;
+        self
+    }
+
+    // Adds a tag pair beyond those set above, written after them in the order added. Tags the
+    // builder writes itself, including FEN and Variant from the starting position, are refused.
+    pub fn tag(&mut self, tag: &str, value: &str) -> Result<&mut Self, TagError> {
+        if RESERVED_TAGS.iter().any(| reserved | reserved.eq_ignore_ascii_case(tag)) { return Err(TagError::Reserved(tag.to_string())); }
+        self.tag_pair_roster.other_tag_pairs.push(PGNGenericTagPair { tag: tag.to_string(), value: escape_tag_value(value) });
+        Ok(self)
+    }
+
+    // Sets both the Result tag and the game termination marker
+    pub fn result(&mut self, result: PGNGameTerminationMarker) -> &mut Self {
+        self.tag_pair_roster.result = result;
+        self.game_termination_marker = result;
+        self
+    }
+
+    // Plays a move given in SAN, which may carry move suffix annotations and NAGs, e.g. Nf3!? $14.
+    // Any check suffix given is replaced by the one the position calls for.
+    pub fn push_san(&mut self, san: &str) -> Result<&mut Self, SanError> {
+        let (remaining, (ply, (_, nags))) = parse_san_ply::<PgnParseError>(san.trim_start()).map_err(| _ | SanError::Syntax(san.to_string()))?;
+        if !remaining.is_empty() { return Err(SanError::Syntax(san.to_string())); }
+        let mv = self.position.resolve_san(&ply)?;
+        self.push_move(&mv, nags);
+        Ok(self)
+    }
+
+    pub fn push_uci(&mut self, uci: &str) -> Result<&mut Self, UciError> {
+        let mv = self.position.resolve_uci(uci)?;
+        self.push_move(&mv, Vec::new());
+        Ok(self)
+    }
+
+    fn push_move(&mut self, mv: &Move, nags: Vec<Nag>) {
+        let (move_number, color, san) = (self.position.fullmove_number(), self.position.side_to_move(), self.position.san(mv));
+        self.position = self.position.play(mv);
+        let check = self.position.check_type().filter(| check | *check != CheckType::StaleMate);
+        self.movetext.push_ply(move_number, color, PGNply { san, check, nags, comments: Vec::new(), variations: Vec::new() });
+    }
+
+    // Comments the last move pushed, or the game as a whole before the first move. Comments are
+    // written in braces, which may hold semicolons and line breaks but not a closing brace.
+    pub fn comment(&mut self, comment: &str) -> Result<&mut Self, AnnotationError> {
+        if comment.contains('}') { return Err(AnnotationError::ClosingBrace(comment.to_string())); }
+        match self.movetext.last_ply_mut() {
+            Some(ply) => ply.comments.push(comment.to_string()),
+            None => self.movetext.leading_comments.push(comment.to_string()),
+        }
+        Ok(self)
+    }
+
+    // Annotates the last move pushed, there being nothing to annotate before the first move
+    pub fn nag(&mut self, nag: Nag) -> Result<&mut Self, AnnotationError> {
+        self.movetext.last_ply_mut().ok_or(AnnotationError::NoPly)?.nags.push(nag);
+        Ok(self)
+    }
+
+    // The game so far, the builder being left as it was so that more moves can be pushed
+    pub fn build(&self) -> PGNFile {
+        PGNFile { tag_pair_roster: self.tag_pair_roster.clone(), movetext: self.movetext.clone(), game_termination_marker: self.game_termination_marker, diagnostics: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn game_builder_test() {
+        let mut builder = GameBuilder::new();
+        builder.event("Casual game").white("Anderssen").black("Kieseritzky").date(Some(1851), Some(6), None).tag("Opening", "King's Gambit").unwrap();
+        assert_eq!(builder.tag("FEN", "8/8/8/8/8/8/8/8 w - - 0 1").unwrap_err().to_string(), "the FEN tag is set by the game builder itself");
+        assert!(builder.tag("result", "1-0").is_err());
+        builder.comment("Played in London").unwrap();
+        assert_eq!(builder.nag(Nag::GOOD_MOVE).unwrap_err().to_string(), "no move has been played to annotate");
+        builder.push_san("e4").unwrap().push_uci("e7e5").unwrap().push_san("f4").unwrap();
+        builder.push_san("exf4!?").unwrap().comment("accepted").unwrap();
+        builder.push_san("Bc4").unwrap().nag(Nag::GOOD_MOVE).unwrap().push_san("Qh4").unwrap();
+        assert_eq!(builder.push_san("Kf3").unwrap_err().to_string(), "no legal move matches 'Kf3'");
+        assert_eq!(builder.push_san("Kz9").unwrap_err().to_string(), "'Kz9' is not SAN");
+        assert_eq!(builder.comment("a}b").unwrap_err().to_string(), "comment 'a}b' contains '}', which would end it when exported");
+        assert_eq!(builder.push_uci("e1f2").unwrap_err().to_string(), "no legal move matches 'e1f2'");
+
+        let game = builder.push_uci("e1f1").unwrap().result(PGNGameTerminationMarker::Undetermined).build();
+        assert!(game.validate().is_ok());
+        assert_eq!(game.to_string(), "[Event \"Casual game\"]\n[Site \"?\"]\n[Date \"1851.06.??\"]\n[Round \"-\"]\n[White \"Anderssen\"]\n[Black \"Kieseritzky\"]\n[Result \"*\"]\n[Time \"??:??:??\"]\n[TimeControl \"?\"]\n[Setup \"0\"]\n[Opening \"King's Gambit\"]\n\n{Played in London} 1. e4 e5 2. f4 exf4!? {accepted} 3. Bc4! Qh4+ 4. Kf1 *");
+
+        // What the builder writes reads back as the same game
+        let mut builder = GameBuilder::new();
+        builder.white("Conor O\"Brien").tag("Annotator", "C:\\").unwrap();
+        builder.push_san("e4").unwrap().comment("; not the end\n[of the comment] {").unwrap().result(PGNGameTerminationMarker::Undetermined);
+        let game = builder.build();
+        let reimported = parse_pgn_game(&game.to_string()).unwrap();
+        assert_eq!(reimported.to_string(), game.to_string());
+        assert_eq!((reimported.tags().white(), game.tags().white()), (Some("Conor O\\\"Brien"), Some("Conor O\\\"Brien")));
+
+        let position: Position = "3k4/8/8/8/8/8/8/4RK1R w K - 0 1".parse().unwrap();
+        let mut builder = GameBuilder::from_position(position);
+        assert_eq!(builder.push_san("O-O").unwrap().position().to_string(), "3k4/8/8/8/8/8/8/4RRK1 b - - 1 1");
+        assert_eq!(builder.build().to_string().matches("[Variant \"Chess960\"]").count(), 1);
+        assert!(builder.tag("Variant", "Chess960").is_err());
+    }
+
+}
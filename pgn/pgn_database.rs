@@ -0,0 +1,300 @@
+use crate::error::*;
+
+use super::*;
+use super::pgn_import::*;
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+use nom::{
+    *,
+    error::*,
+    combinator::*,
+    sequence::*,
+    character::complete::*,
+  };
+
+// Blank lines and stray commentary that can sit between two games in a PGN database
+pub fn parse_inter_game_text<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
+    value((), pair(multispace0, opt(parse_commentry)))(input)
+}
+
+// Skips past the tag pair section of a game that failed to parse and returns the input
+// from the start of the next tag pair section, i.e. the next game in the database
+pub fn find_next_game(input: &str) -> &str {
+    let mut in_tag_section = true;
+    let mut offset = 0usize;
+    for line in input.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            if !in_tag_section { return &input[offset..]; }
+        } else if !trimmed.is_empty() {
+            in_tag_section = false;
+        }
+        offset += line.len();
+    }
+    ""
+}
+
+// Iterates over a multi-game PGN database, yielding one game at a time. A game that fails
+// to parse is returned as an error and the reader moves on to the following game.
+pub struct PgnDatabaseReader<'a> {
+    source: &'a str,
+    input: &'a str,
+    games_read: usize,
+    mode: ImportMode,
+}
+
+impl<'a> PgnDatabaseReader<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_mode(input, ImportMode::Strict)
+    }
+
+    pub fn with_mode(input: &'a str, mode: ImportMode) -> Self {
+        PgnDatabaseReader { source: input, input, games_read: 0, mode }
+    }
+}
+
+impl Iterator for PgnDatabaseReader<'_> {
+    type Item = Result<PGNFile, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // parse_inter_game_text cannot fail, at worst it consumes nothing
+        let mut input = self.input;
+        while let Ok((remaining, _)) = parse_inter_game_text::<nom::error::Error<_>>(input) {
+            if remaining.len() == input.len() { break; }
+            input = remaining;
+        }
+        self.input = input;
+
+        if self.input.is_empty() { return None; }
+        self.games_read += 1;
+
+        let parsed = match self.mode {
+            ImportMode::Strict => parse_pgn_file::<PgnParseError>(self.input).map_err(| err | PgnError::from_parse_error(self.source, err)),
+            ImportMode::Lenient(recovery) => parse_pgn_file_lenient(self.source, self.input, recovery),
+        };
+
+        match parsed {
+            Ok((remaining, game)) => {
+                self.input = remaining;
+                Some(Ok(game))
+            },
+            Err(err) => {
+                let err = err.with_game(self.games_read);
+                self.input = find_next_game(self.input);
+                Some(Err(err))
+            },
+        }
+    }
+}
+
+// Reads games from any buffered source without loading the whole database into memory.
+// Lines are accumulated until the start of the next tag pair section is seen, so at most
+// one game (plus any commentary before it) is held at a time.
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    line: Vec<u8>,
+    lines_read: usize,
+    next_game_text: String,
+    next_game_line: usize,
+    games_read: usize,
+    games: VecDeque<Result<PGNFile, PgnError>>,
+    finished: bool,
+    mode: ImportMode,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_mode(reader, ImportMode::Strict)
+    }
+
+    pub fn with_mode(reader: R, mode: ImportMode) -> Self {
+        PgnReader {
+            reader,
+            line: Vec::new(),
+            lines_read: 0,
+            next_game_text: String::new(),
+            next_game_line: 0,
+            games_read: 0,
+            games: VecDeque::new(),
+            finished: false,
+            mode,
+        }
+    }
+
+    // Returns the text of the next game along with the number of lines before it, or None
+    // once the source is exhausted
+    fn read_game_text(&mut self) -> Result<Option<(String, usize)>, std::io::Error> {
+        let mut game_text = std::mem::take(&mut self.next_game_text);
+        let game_line = if game_text.is_empty() { self.lines_read } else { self.next_game_line };
+        let mut seen_movetext = false;
+        let mut in_braced_comment = false;
+
+        loop {
+            self.line.clear();
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                self.finished = true;
+                break;
+            }
+            self.lines_read += 1;
+            // A line that is not UTF-8 is taken to be Latin-1, in which older databases are often written
+            let line: Cow<str> = match std::str::from_utf8(&self.line) {
+                Ok(line) => Cow::Borrowed(line),
+                Err(_) => Cow::Owned(self.line.iter().map(| &byte | byte as char).collect()),
+            };
+            let trimmed = line.trim_start();
+
+            if !in_braced_comment && seen_movetext && trimmed.starts_with('[') {
+                self.next_game_text = line.into_owned();
+                self.next_game_line = self.lines_read - 1;
+                break;
+            }
+
+            if !in_braced_comment && !trimmed.is_empty() && !trimmed.starts_with('[') {
+                seen_movetext = true;
+            }
+            // Braces and semicolons in a tag value are part of the value, which ends with the line
+            let mut in_tag_value = false;
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '}' if in_braced_comment => in_braced_comment = false,
+                    _ if in_braced_comment => (),
+                    '\\' if in_tag_value => { chars.next(); },
+                    '"' => in_tag_value = !in_tag_value,
+                    _ if in_tag_value => (),
+                    '{' => in_braced_comment = true,
+                    ';' => break,
+                    _ => (),
+                }
+            }
+            game_text.push_str(&line);
+        }
+
+        if game_text.is_empty() { Ok(None) } else { Ok(Some((game_text, game_line))) }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PGNFile, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.games.is_empty() && !self.finished {
+            match self.read_game_text() {
+                Ok(Some((game_text, game_line))) => {
+                    for game in PgnDatabaseReader::with_mode(&game_text, self.mode) {
+                        self.games_read += 1;
+                        let game = game.map(| mut game | {
+                            game.diagnostics.iter_mut().for_each(| diagnostic | diagnostic.line += game_line);
+                            game
+                        });
+                        self.games.push_back(game.map_err(| err | err.with_line_offset(game_line).with_game(self.games_read)));
+                    }
+                },
+                Ok(None) => (),
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(PgnError::from_io_error(err, self.lines_read + 1)));
+                },
+            }
+        }
+        self.games.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn database_reader_tests() {
+
+        let input = "[Event \"Game 1\"]
+        [Result \"1-0\"]
+
+        1. e4 e5 2. Nf3 Nc6 1-0
+
+        {Stray commentary between games}
+        ; and a rest of line comment
+
+        [Event \"Game 2\"]
+        [Result \"*\"]
+
+        1. e4 e5 2. Nz9 Nc6 *
+
+        [Event \"Game 3\"]
+        [Result \"0-1\"]
+
+        1. d4 d5 0-1
+        ";
+
+        let games: Vec<_> = PgnDatabaseReader::new(input).collect();
+
+        assert_eq!(games.len(), 3);
+        assert!(games[0].is_ok());
+        assert_eq!(games[1].as_ref().unwrap_err().to_string(), "expected square after 'N', found 'z9' at 12:22 in game 2");
+        assert!(games[2].is_ok());
+        assert!(games[2].as_ref().unwrap().to_string().starts_with("[Event \"Game 3\"]"));
+        assert!(games[2].as_ref().unwrap().to_string().ends_with("1. d4 d5 0-1"));
+
+        // Unplayed games, such as forfeits, have no moves
+        let input = "[Event \"Game 1\"]\n[Result \"1-0\"]\n\n1-0\n\n[Event \"Game 2\"]\n[Result \"0-1\"]\n\n{Forfeit} 0-1\n\n[Event \"Game 3\"]\n\n1. d4 d5 *\n";
+        let games: Vec<_> = PgnDatabaseReader::new(input).collect();
+        assert_eq!(games.len(), 3);
+        assert!(games[0].as_ref().unwrap().to_string().ends_with("\n\n1-0"));
+        assert!(games[1].as_ref().unwrap().to_string().ends_with("\n\n{Forfeit} 0-1"));
+        assert!(games[2].is_ok());
+        assert_eq!(PgnReader::new(input.as_bytes()).filter(Result::is_ok).count(), 3);
+
+        assert_eq!(PgnDatabaseReader::new("  \n\n").count(), 0);
+
+    }
+
+    #[test]
+    fn buffered_reader_tests() {
+
+        let input = "{Database header comment}
+[Event \"Game 1\"]
+[Result \"1-0\"]
+
+1. e4 e5 {A comment spanning lines
+[Not a tag pair]} 2. Nf3 Nc6 1-0
+[Event \"Game 2\"]
+[Result \"*\"]
+
+1. e4 e5 2. Nz9 Nc6 *
+
+[Event \"Game 3\"] [Result \"0-1\"] 1. d4 d5 0-1";
+
+        let games: Vec<_> = PgnReader::new(std::io::BufReader::with_capacity(16, input.as_bytes())).collect();
+
+        assert_eq!(games.len(), 3);
+        assert!(games[0].as_ref().unwrap().to_string().ends_with("1. e4 e5 {A comment spanning lines\n[Not a tag pair]} 2. Nf3 Nc6 1-0"));
+        let err = games[1].as_ref().unwrap_err();
+        assert_eq!((err.line(), err.column(), err.game()), (10, 14, Some(2)));
+        assert!(games[2].as_ref().unwrap().to_string().ends_with("1. d4 d5 0-1"));
+
+        let games: Vec<_> = PgnReader::with_mode(input.as_bytes(), ImportMode::Lenient(LenientRecovery::ResyncAtNextGame)).collect();
+
+        assert_eq!(games.len(), 3);
+        let game = games[1].as_ref().unwrap();
+        assert!(game.to_string().ends_with("1. e4 e5 *"));
+        assert_eq!((game.diagnostics()[0].line(), game.diagnostics()[0].column()), (10, 14));
+
+        // A brace in a tag value does not open a comment, so the games are still read one at a time
+        let input = "[Event \"Game 1\"]\n[Annotator \"x{\"]\n\n1. e4 e5 *\n[Event \"Game 2\"]\n\n1. d4 d5 *\n";
+        let mut reader = PgnReader::new(input.as_bytes());
+        assert_eq!(reader.read_game_text().unwrap(), Some(("[Event \"Game 1\"]\n[Annotator \"x{\"]\n\n1. e4 e5 *\n".to_string(), 0)));
+        assert_eq!(reader.count(), 1);
+
+        // Latin-1 is read as such rather than replaced
+        let input = b"[Event \"Game 1\"]\n[White \"M\xfcller\"]\n\n1. e4 e5 *\n";
+        let games: Vec<_> = PgnReader::new(&input[..]).collect();
+        assert_eq!(games[0].as_ref().unwrap().tags().white(), Some("M\u{fc}ller"));
+
+    }
+
+}
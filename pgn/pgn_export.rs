@@ -1,5 +1,44 @@
 use super::*;
 
+// Export Options
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NagStyle {
+    #[default]
+    Symbolic, // NAGs 1 to 6 written as the traditional move suffix, e.g. e4!! rather than e4 $3
+    Numeric, // Every NAG written in its $n form
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SanStyle {
+    #[default]
+    AsImported, // Each ply written with the qualifiers and check suffix it was imported with
+    Canonical, // Each ply written as the SAN generated from the position it is played in
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PGNExportOptions {
+    pub nag_style: NagStyle,
+    pub san_style: SanStyle,
+}
+
+// Displays a PGN item using the given export options rather than the defaults
+pub struct PGNExport<'a, T> {
+    item: &'a T,
+    options: PGNExportOptions,
+}
+
+impl PGNFile {
+    pub fn export(&self, options: PGNExportOptions) -> PGNExport<'_, PGNFile> {
+        PGNExport { item: self, options }
+    }
+}
+
+impl PGNmovetext {
+    pub fn export(&self, options: PGNExportOptions) -> PGNExport<'_, PGNmovetext> {
+        PGNExport { item: self, options }
+    }
+}
+
 // Movetext Section
 impl fmt::Display for SANPlyCoordinates {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -13,10 +52,10 @@ impl fmt::Display for SANply {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SANply::Basic { piece_moved, mv }
-                if (*piece_moved == PieceType::PawnsWhite) || (*piece_moved == PieceType::PawnsBlack) => { write!(f, "{}", mv) }
+                if *piece_moved == Role::Pawn => { write!(f, "{}", mv) }
             SANply::Basic { piece_moved, mv } => write!(f, "{}{}", piece_moved, mv),
             SANply::Capture { piece_moved, mv,
-            } if (*piece_moved == PieceType::PawnsWhite) || (*piece_moved == PieceType::PawnsBlack) =>
+            } if *piece_moved == Role::Pawn =>
             {
                 match &mv.from_file {
                     Some(file) => write!(f, "{}x{}", file, mv.to_square),
@@ -46,24 +85,135 @@ impl fmt::Display for SANply {
     }
 }
 
+impl fmt::Display for CheckType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckType::Check => write!(f, "+"),
+            CheckType::CheckMate => write!(f, "#"),
+            CheckType::StaleMate => write!(f, ""),
+        }
+    }
+}
+
+impl fmt::Display for Nag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${}", self.0)
+    }
+}
+
+// Only the first NAG can be written as a suffix, any others follow in the $n form
+fn write_ply_annotations(f: &mut fmt::Formatter<'_>, check: Option<CheckType>, nags: &[Nag], options: &PGNExportOptions) -> fmt::Result {
+    if let Some(check) = check { write!(f, "{}", check)?; }
+    let mut nags = nags.iter().peekable();
+    if options.nag_style == NagStyle::Symbolic {
+        if let Some(suffix) = nags.peek().and_then(| nag | nag.suffix()) {
+            write!(f, "{}", suffix)?;
+            nags.next();
+        }
+    }
+    for nag in nags {
+        write!(f, " {}", nag)?;
+    }
+    write!(f, "")
+}
+
+impl fmt::Display for PGNply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.san)?;
+        write_ply_annotations(f, self.check, &self.nags, &PGNExportOptions::default())
+    }
+}
+
 impl fmt::Display for PGNmove {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.white_ply)?;
-        if let Some(white_ply_annotation) = &self.white_ply_annotation { write!(f, "{}", white_ply_annotation)?; }
-        if let Some(black_move) = &self.black_ply { write!(f, " {}", black_move)?; }
-        if let Some(black_ply_annotation) = &self.black_ply_annotation { write!(f, "{}", black_ply_annotation)?; }
-        write!(f, "")
+        match (&self.white_ply, &self.black_ply) {
+            (Some(white_ply), Some(black_ply)) => write!(f, "{} {}", white_ply, black_ply),
+            (Some(white_ply), None) => write!(f, "{}", white_ply),
+            (None, Some(black_ply)) => write!(f, "{}", black_ply),
+            (None, None) => write!(f, ""),
+        }
+    }
+}
+
+fn write_comments(f: &mut fmt::Formatter<'_>, comments: &[String]) -> fmt::Result {
+    for comment in comments {
+        write!(f, " {{{}}}", comment)?;
+    }
+    write!(f, "")
+}
+
+// Writes a ply followed by its comments and then each of its variations, which branch from
+// the same move number. Rest of line comments are written in the braced form.
+//
+// When writing canonical SAN the position is the one the ply is played in, and the position it
+// leads to is returned. A ply that cannot be played, and the rest of its line, is written as
+// imported.
+fn write_ply(f: &mut fmt::Formatter<'_>, ply: &PGNply, color: Color, options: &PGNExportOptions, position: Option<&Position>) -> Result<Option<Position>, fmt::Error> {
+    let played = position.filter(| position | position.side_to_move() == color)
+        .and_then(| position | position.resolve_san(&ply.san).ok().map(| mv | (position.san(&mv), position.play(&mv))));
+    match &played {
+        Some((san, next_position)) => {
+            write!(f, "{}", san)?;
+            write_ply_annotations(f, next_position.check_type(), &ply.nags, options)?;
+        },
+        None => {
+            write!(f, "{}", ply.san)?;
+            write_ply_annotations(f, ply.check, &ply.nags, options)?;
+        },
+    }
+    write_comments(f, &ply.comments)?;
+    for variation in &ply.variations {
+        write!(f, " (")?;
+        write_movetext(f, variation, options, position.cloned())?;
+        write!(f, ")")?;
+    }
+    Ok(played.map(| (_, next_position) | next_position))
+}
+
+// Writes the moves separated by single spaces, numbered as they were imported. Black's move is
+// given its own move number when it starts a line or follows a comment or variation on white's ply.
+fn write_movetext(f: &mut fmt::Formatter<'_>, movetext: &PGNmovetext, options: &PGNExportOptions, mut position: Option<Position>) -> fmt::Result {
+    if let Some((first_comment, comments)) = movetext.leading_comments.split_first() {
+        write!(f, "{{{}}}", first_comment)?;
+        write_comments(f, comments)?;
+        if !movetext.moves.is_empty() { write!(f, " ")?; }
+    }
+    for (index, mv) in movetext.moves.iter().enumerate() {
+        let move_number = mv.move_number;
+        if index > 0 { write!(f, " ")?; }
+        if let Some(white_ply) = &mv.white_ply {
+            write!(f, "{}. ", move_number)?;
+            position = write_ply(f, white_ply, Color::White, options, position.as_ref())?;
+        }
+        if let Some(black_ply) = &mv.black_ply {
+            match &mv.white_ply {
+                Some(white_ply) if white_ply.comments.is_empty() && white_ply.variations.is_empty() => write!(f, " ")?,
+                Some(_) => write!(f, " {}... ", move_number)?,
+                None => write!(f, "{}... ", move_number)?,
+            }
+            position = write_ply(f, black_ply, Color::Black, options, position.as_ref())?;
+        }
+    }
+    write!(f, "")
+}
+
+// Canonical SAN for the movetext alone is generated from the standard starting position
+fn write_movetext_section(f: &mut fmt::Formatter<'_>, movetext: &PGNmovetext, options: &PGNExportOptions, starting_position: impl FnOnce() -> Position) -> fmt::Result {
+    let position = (options.san_style == SanStyle::Canonical).then(starting_position);
+    write_movetext(f, movetext, options, position)?;
+    if !movetext.moves.is_empty() || !movetext.leading_comments.is_empty() { write!(f, " ")?; }
+    write!(f, "")
+}
+
+impl fmt::Display for PGNExport<'_, PGNmovetext> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_movetext_section(f, self.item, &self.options, Position::default)
     }
 }
 
 impl fmt::Display for PGNmovetext {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut move_count: usize = 1usize;
-        for mv in &self.moves {
-            write!(f, "{}. {} ", move_count, mv)?;
-            move_count += 1;
-        }
-        write!(f, "")
+        write!(f, "{}", self.export(PGNExportOptions::default()))
     }
 }
 
@@ -153,10 +303,10 @@ impl fmt::Display for PGNTagPairRoster {
         writeln!(f, "[Result \"{}\"]", self.result)?;
         writeln!(f, "[Time \"{}\"]", self.time)?;
         writeln!(f, "[TimeControl \"{}\"]", self.time_control)?;
-        match &self.fen_string {
-            Some(fen_string) => {
+        match &self.fen {
+            Some(position) => {
                 writeln!(f, "[Setup \"1\"]")?;
-                writeln!(f, "[FEN \"{}\"]", fen_string)?;
+                writeln!(f, "[FEN \"{}\"]", position)?;
             },
             None => writeln!(f, "[Setup \"0\"]")?,
         };
@@ -168,11 +318,18 @@ impl fmt::Display for PGNTagPairRoster {
 }
 
 //Overall PGN File
+impl fmt::Display for PGNExport<'_, PGNFile> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.item.tag_pair_roster)?;
+        writeln!(f)?;
+        write_movetext_section(f, &self.item.movetext, &self.options, | | self.item.starting_position())?;
+        write!(f, "{}", self.item.game_termination_marker)
+    }
+}
+
 impl fmt::Display for PGNFile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.tag_pair_roster)?;
-        write!(f, "\n{}", self.movetext)?;
-        write!(f, "{}", self.game_termination_marker)
+        write!(f, "{}", self.export(PGNExportOptions::default()))
     }
 }
 
@@ -187,8 +344,8 @@ use super::*;
     fn import_sanply_failure_test1() {
 
         let move_input = SANply::Capture { 
-            piece_moved: PieceType::PawnsWhite, 
-            mv: SANPlyCoordinates { from_file: None, from_rank: None, to_square: Square { reference: 34 } } 
+            piece_moved: Role::Pawn, 
+            mv: SANPlyCoordinates { from_file: None, from_rank: None, to_square: Square::new(File::C, Rank::R5) } 
         };
 
         let _ = move_input.to_string();
@@ -200,12 +357,48 @@ use super::*;
     fn import_sanply_failure_test2() {
 
         let move_input = SANply::CapturePromotion { 
-            piece_promoted: PieceType::Queen, 
-            mv: SANPlyCoordinates { from_file: None, from_rank: None, to_square: Square { reference: 34 } } 
+            piece_promoted: Role::Queen, 
+            mv: SANPlyCoordinates { from_file: None, from_rank: None, to_square: Square::new(File::C, Rank::R5) } 
         };
 
         let _ = move_input.to_string();
         
     }
 
+    #[test]
+    fn export_nag_style_test() {
+
+        let movetext_input = "1. e4!! $14 e5? 2. Qh5+?! $2 Nc6 3. Bc4 Nf6?? 4. Qxf7# $1";
+        let (_, movetext) = crate::pgn::pgn_import::parse_san_movetext::<nom::error::Error<_>>(movetext_input).unwrap();
+
+        assert_eq!(movetext.to_string(), "1. e4!! $14 e5? 2. Qh5+?! $2 Nc6 3. Bc4 Nf6?? 4. Qxf7#! ");
+        assert_eq!(
+            movetext.export(PGNExportOptions { nag_style: NagStyle::Numeric, ..PGNExportOptions::default() }).to_string(),
+            "1. e4 $3 $14 e5 $2 2. Qh5+ $6 $2 Nc6 3. Bc4 Nf6 $4 4. Qxf7# $1 "
+        );
+
+        let white_ply = movetext.moves[1].white_ply.as_ref().unwrap();
+        assert_eq!(white_ply.check, Some(CheckType::Check));
+        assert_eq!(white_ply.nags, vec![Nag::DUBIOUS_MOVE, Nag::MISTAKE]);
+        assert!(movetext.moves[2].black_ply.as_ref().unwrap().is_blunder());
+        assert!(!movetext.moves[2].white_ply.as_ref().unwrap().is_blunder());
+
+        assert_eq!(Nag::from_suffix("?!"), Some(Nag::DUBIOUS_MOVE));
+        assert_eq!(Nag(120).suffix(), None);
+
+    }
+
+
+    #[test]
+    fn canonical_san_export_test() {
+        let options = PGNExportOptions { san_style: SanStyle::Canonical, ..PGNExportOptions::default() };
+        let canonical = | pgn: &str | crate::pgn::pgn_import::parse_pgn_game(&format!("[Event \"?\"] {}", pgn)).unwrap().movetext.export(options).to_string();
+        let normalised = "1. d4 d5 2. Nf3 Nf6 3. Nbd2 Nbd7 4. e4 dxe4 5. Nxe4 Nxe4 ";
+        assert_eq!(canonical("1. d4 d5 2. Ng1f3 Ng8f6 3. Nb1d2 Nbd7 4. e4 dxe4 5. Nd2xe4 Nf6xe4+ *"), normalised);
+        assert_eq!(canonical("1. d4 d5 2. Nf3 Nf6 3. Nbd2 Nbd7 4. e4 dxe4 5. Nxe4 Nxe4 *"), normalised);
+
+        // The ambiguous Nd2 and everything after it in the line is written as imported
+        assert_eq!(canonical("1. d4 d5 2. Nf3 Nf6 3. Nd2 (3. Nb1d2 Qd6+) Nbd7 *"), "1. d4 d5 2. Nf3 Nf6 3. Nd2 (3. Nbd2 Qd6) 3... Nbd7 ");
+    }
+
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use crate::time_controls::*;
+use crate::error::*;
 
 use super::*;
+use super::pgn_database::find_next_game;
 
 use nom::{
     *,
@@ -13,36 +15,70 @@ use nom::{
     branch::*,
   };
 
-// Parse Comments and unwanted data, functions throw away the PGN data
-pub fn parse_comment_rest_of_line<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E>{
-    value((), tuple((tag(";"), not_line_ending, line_ending, multispace0)))(input)
+// Parse Comments, the text of each comment is kept whilst escaped data is thrown away
+pub fn parse_comment_rest_of_line<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, String, E>{
+    map(
+        delimited(tag(";"), not_line_ending, pair(line_ending, multispace0)),
+        | comment: &str | comment.to_string()
+    )(input)
 }
 
-pub fn parse_comment_braced<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
-    value((), tuple((tag("{"), take_until("}"), tag("}"), multispace0)))(input)
+pub fn parse_comment_braced<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, String, E> {
+    map(
+        delimited(tag("{"), take_until("}"), pair(tag("}"), multispace0)),
+        | comment: &str | comment.to_string()
+    )(input)
 }
 
 pub fn parse_escape_mechanism<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
     value((), tuple((opt(line_ending), tag("%"), not_line_ending, line_ending, multispace0)))(input)
 }
 
-// Will fail for recursive varations as it stands 
-pub fn parse_annotation_variation<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
-    value((), tuple((tag("("), take_until(")"), tag(")"), multispace0)))(input)                    
+pub fn parse_commentry<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Vec<String>, E> {
+    map(
+        many1(alt((
+            map(parse_comment_rest_of_line, Some), 
+            map(parse_comment_braced, Some), 
+            map(parse_escape_mechanism, | _ | None), 
+        ))),
+        | comments | comments.into_iter().flatten().collect()
+    )(input)
+}
+
+// Parse Recursive Annotation Variations, these nest to any depth
+pub fn parse_annotation_variation<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNmovetext, E> {
+    delimited(
+        pair(char('('), multispace0),
+        parse_san_movetext,
+        pair(char(')'), multispace0),
+    )(input)
 }
 
-pub fn parse_commentry<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
-    value((), many1(alt((
-        parse_comment_rest_of_line, 
-        parse_comment_braced, 
-        parse_escape_mechanism, 
-        parse_annotation_variation,
-    ))))(input)
+// Comments, NAGs and variations following a ply in any order, as (comments, nags, variations)
+pub type PlySuffix = (Vec<String>, Vec<Nag>, Vec<PGNmovetext>);
+
+pub fn parse_ply_suffix<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, PlySuffix, E> {
+    let (input, suffixes) = many0(alt((
+        map(parse_annotation_variation, | variation | (Vec::new(), None, Some(variation))),
+        map(parse_commentry, | comments | (comments, None, None)),
+        map(terminated(parse_nag, multispace0), | nag | (Vec::new(), Some(nag), None)),
+    )))(input)?;
+
+    let mut comments = Vec::new();
+    let mut nags = Vec::new();
+    let mut variations = Vec::new();
+    for (suffix_comments, nag, variation) in suffixes {
+        comments.extend(suffix_comments);
+        nags.extend(nag);
+        variations.extend(variation);
+    }
+    Ok((input, (comments, nags, variations)))
 }
 
 // Parse Movetext
+// A single period, N... is the move number indication for black's ply
 pub fn parse_move_number<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, u32, E> {
-    terminated(preceded(multispace0, u32), pair(char('.'), multispace0))(input)
+    terminated(preceded(multispace0, u32), tuple((char('.'), not(char('.')), multispace0)))(input)
 }
 
 pub fn parse_move_number_after_annotation<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, u32, E> {
@@ -50,13 +86,13 @@ pub fn parse_move_number_after_annotation<'a, E: ParseError<&'a str>>(input: &'a
 }
 
 // Parse SAN
-pub fn parse_piece<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, PieceType, E> {
+pub fn parse_piece<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Role, E> {
     alt((
-        value(PieceType::King, char('K')),
-        value(PieceType::Queen, char('Q')),
-        value(PieceType::Rook, char('R')),
-        value(PieceType::Bishop, char('B')),
-        value(PieceType::Knight, char('N')),
+        value(Role::King, char('K')),
+        value(Role::Queen, char('Q')),
+        value(Role::Rook, char('R')),
+        value(Role::Bishop, char('B')),
+        value(Role::Knight, char('N')),
     ))(input)
 }
 
@@ -86,25 +122,57 @@ pub fn parse_rank<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str
     ))(input)
 }
 
-pub fn parse_square<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Square, E> {
-    map(pair(parse_file, parse_rank), | (file, rank) | Square { reference: ((rank as u8) * 8 + (file as u8)) as usize})(input)
+pub fn parse_square<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Square, E> {
+    context("square", map(pair(parse_file, parse_rank), | (file, rank) | Square::new(file, rank)))(input)
 }
 
 pub fn parse_capture<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, bool, E> {
     value(true, char('x'))(input)
 }
 
-pub fn parse_promotion<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, PieceType, E> {
+pub fn parse_promotion<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Role, E> {
     alt((
-        value(PieceType::Queen, tag("=Q")),
-        value(PieceType::Rook, tag("=R")),
-        value(PieceType::Bishop, tag("=B")),
-        value(PieceType::Knight, tag("=N")),
+        value(Role::Queen, tag("=Q")),
+        value(Role::Rook, tag("=R")),
+        value(Role::Bishop, tag("=B")),
+        value(Role::Knight, tag("=N")),
     ))(input)
 }
 
-pub fn parse_checks_and_nag<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, String, E> {
-    map(recognize(many0(none_of( "= {(;\t\n\r"))), | check_nag_and_end_token: &str | check_nag_and_end_token.to_string())(input)
+pub fn parse_check<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, CheckType, E> {
+    alt((
+        value(CheckType::Check, char('+')),
+        value(CheckType::CheckMate, char('#')),
+    ))(input)
+}
+
+// Two character suffixes must be tried before the single character ones
+pub fn parse_move_suffix_annotation<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Nag, E> {
+    alt((
+        value(Nag::BRILLIANT_MOVE, tag("!!")),
+        value(Nag::BLUNDER, tag("??")),
+        value(Nag::SPECULATIVE_MOVE, tag("!?")),
+        value(Nag::DUBIOUS_MOVE, tag("?!")),
+        value(Nag::GOOD_MOVE, tag("!")),
+        value(Nag::MISTAKE, tag("?")),
+    ))(input)
+}
+
+pub fn parse_nag<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Nag, E> {
+    map(preceded(char('$'), u8), Nag)(input)
+}
+
+// Suffix annotations are stored as their equivalent NAG
+pub type PlyAnnotations = (Option<CheckType>, Vec<Nag>);
+
+pub fn parse_checks_and_nag<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, PlyAnnotations, E> {
+    let (input, (check, suffix_annotation, mut nags)) = tuple((
+        opt(parse_check),
+        opt(parse_move_suffix_annotation),
+        many0(preceded(multispace0, parse_nag)),
+    ))(input)?;
+    if let Some(nag) = suffix_annotation { nags.insert(0, nag); }
+    Ok((input, (check, nags)))
 }
 
 fn parse_san_castle<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
@@ -114,7 +182,7 @@ fn parse_san_castle<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a s
     ))(input)
 }
 
-fn parse_san_capture_promotion<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
+fn parse_san_capture_promotion<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
     let (input, (from_file, to_square, piece_promoted)) = tuple((
         parse_file,
         preceded(parse_capture, parse_square),
@@ -124,7 +192,7 @@ fn parse_san_capture_promotion<'a, E: ParseError<&'a str>>(input: &'a str) -> IR
     Ok((input, SANply::CapturePromotion { mv, piece_promoted }))
 }
 
-fn parse_san_promotion<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
+fn parse_san_promotion<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
     let (input, (to_square, piece_promoted)) = tuple((
         parse_square,
         parse_promotion,
@@ -133,7 +201,8 @@ fn parse_san_promotion<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'
     Ok((input, SANply::Promotion { mv, piece_promoted }))
 }
 
-fn parse_san_capture<'a, E: ParseError<&'a str>>(input: &'a str, default_piece: PieceType) -> IResult<&'a str, SANply, E> {
+// A move without a piece letter is a pawn's, whichever side plays it
+fn parse_san_capture<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
     
     let (input, (piece, from_file, from_rank, to_square)) = tuple((
         opt(parse_piece),
@@ -143,19 +212,12 @@ fn parse_san_capture<'a, E: ParseError<&'a str>>(input: &'a str, default_piece:
     ))(input)?;
     
     let mv: SANPlyCoordinates = SANPlyCoordinates {from_file, from_rank, to_square };
-    let piece_moved = piece.unwrap_or(default_piece);
+    let piece_moved = piece.unwrap_or(Role::Pawn);
     Ok((input, SANply::Capture { piece_moved, mv }))
 
 }
 
-fn parse_san_capture_white<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
-    parse_san_capture(input, PieceType::PawnsWhite)
-}
-fn parse_san_capture_black<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
-    parse_san_capture(input, PieceType::PawnsBlack)
-}
-
-fn parse_san_basic_qualified<'a, E: ParseError<&'a str>>(input: &'a str, default_piece: PieceType) -> IResult<&'a str, SANply, E> {
+fn parse_san_basic_qualified<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
     
     let (input, (piece, from_file, from_rank, to_square)) = tuple((
         opt(parse_piece),
@@ -165,19 +227,12 @@ fn parse_san_basic_qualified<'a, E: ParseError<&'a str>>(input: &'a str, default
     ))(input)?;
     
     let mv: SANPlyCoordinates = SANPlyCoordinates { from_file, from_rank, to_square };
-    let piece_moved = piece.unwrap_or(default_piece);
+    let piece_moved = piece.unwrap_or(Role::Pawn);
     Ok((input, SANply::Basic { piece_moved, mv }))
 
 }
 
-fn parse_san_basic_qualified_white<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
-    parse_san_basic_qualified(input, PieceType::PawnsWhite)
-}
-fn parse_san_basic_qualified_black<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
-    parse_san_basic_qualified(input, PieceType::PawnsBlack)
-}
-
-fn parse_san_basic_unqualified<'a, E: ParseError<&'a str>>(input: &'a str, default_piece: PieceType) -> IResult<&'a str, SANply, E> {
+fn parse_san_basic_unqualified<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
     
     let (input, (piece, to_square)) = tuple((
         opt(parse_piece),
@@ -185,68 +240,106 @@ fn parse_san_basic_unqualified<'a, E: ParseError<&'a str>>(input: &'a str, defau
     ))(input)?;
     
     let mv: SANPlyCoordinates = SANPlyCoordinates {from_file: None, from_rank: None, to_square };
-    let piece_moved = piece.unwrap_or(default_piece);
+    let piece_moved = piece.unwrap_or(Role::Pawn);
     Ok((input, SANply::Basic { piece_moved, mv }))
 
 }
 
-fn parse_san_basic_unqualified_white<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
-    parse_san_basic_unqualified(input, PieceType::PawnsWhite)
-}
-fn parse_san_basic_unqualified_black<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, SANply, E> {
-    parse_san_basic_unqualified(input, PieceType::PawnsBlack)
-}
-
-pub fn parse_san_ply_white<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (SANply, Option<String>), E> {
+pub fn parse_san_ply<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, (SANply, PlyAnnotations), E> {
     tuple((
         alt((
             parse_san_castle, 
             parse_san_capture_promotion, 
             parse_san_promotion, 
-            parse_san_capture_white,
-            parse_san_basic_qualified_white,
-            parse_san_basic_unqualified_white,
+            parse_san_capture,
+            parse_san_basic_qualified,
+            parse_san_basic_unqualified,
         )),
         terminated(
-            opt(parse_checks_and_nag),
+            parse_checks_and_nag,
             multispace0)
     ))(input)
 }
 
-pub fn parse_san_ply_black<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (SANply, Option<String>), E> {
-    tuple((
-        alt((
-            parse_san_castle, 
-            parse_san_capture_promotion, 
-            parse_san_promotion, 
-            parse_san_capture_black,
-            parse_san_basic_qualified_black,
-            parse_san_basic_unqualified_black,
-        )),
-        terminated(
-            opt(parse_checks_and_nag),
-            multispace0)
-    ))(input)
+pub fn parse_pgn_ply<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNply, E> {
+    map(
+        pair(parse_san_ply, parse_ply_suffix),
+        | ((san, (check, mut nags)), (comments, suffix_nags, variations)) | {
+            nags.extend(suffix_nags);
+            PGNply { san, check, nags, comments, variations }
+        }
+    )(input)
 }
 
-pub fn parse_san_move<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNmove, E> {
+// Move numbering
 
-    let (input, (
-        (white_ply, white_ply_annotation), 
-        _comment1,
-        black_ply_and_annotation,
-        _comment2,
-    )) = tuple((
-        preceded(parse_move_number, parse_san_ply_white), 
-        opt(pair(parse_commentry, parse_move_number_after_annotation)),
-        opt(parse_san_ply_black),
-        opt(parse_commentry),
-    ))(input)?;
-    match black_ply_and_annotation {
-        Some((black_ply, black_ply_annotation)) => Ok((input, PGNmove { white_ply, white_ply_annotation, black_ply: Some(black_ply), black_ply_annotation })),
-        None => Ok((input, PGNmove { white_ply, white_ply_annotation, black_ply: None, black_ply_annotation: None })),
+// The move number of a move and the side whose ply it starts with
+pub type MoveNumbering = (u32, Color);
+
+pub fn move_numbering(mv: &PGNmove) -> MoveNumbering {
+    match mv.white_ply {
+        Some(_) => (mv.move_number, Color::White),
+        None => (mv.move_number, Color::Black),
+    }
+}
+
+pub fn next_move_numbering(mv: &PGNmove) -> MoveNumbering {
+    (mv.move_number + 1, Color::White)
+}
+
+// Numbering of the first move of a game, taken from the fullmove number and side to move of the FEN tag
+pub fn starting_move_numbering(tag_pair_roster: &PGNTagPairRoster) -> MoveNumbering {
+    match &tag_pair_roster.fen {
+        Some(position) => (position.fullmove_number(), position.side_to_move()),
+        None => (1, Color::White),
     }
+}
+
+pub fn move_numbering_label((move_number, color): MoveNumbering) -> String {
+    match color {
+        Color::White => format!("{}.", move_number),
+        Color::Black => format!("{}...", move_number),
+    }
+}
 
+// A variation replaces the ply it follows, so must start with the same move number and side
+fn variations_numbered_from(ply: &PGNply, numbering: MoveNumbering) -> bool {
+    ply.variations.iter().all(| variation | variation.moves.first().is_none_or(| mv | move_numbering(mv) == numbering))
+}
+
+fn move_number_failure<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str, expected: &'static str) -> nom::Err<E> {
+    let input = input.trim_start();
+    nom::Err::Failure(E::add_context(input, expected, E::from_error_kind(input, ErrorKind::Verify)))
+}
+
+pub fn parse_san_move<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNmove, E> {
+
+    // Once a move number has been read a ply must follow it
+    let move_input = input;
+    let (input, (move_number, white_ply)) = pair(parse_move_number, cut(context("SAN move", parse_pgn_ply)))(input)?;
+    let black_input = input;
+    let (input, black_ply) = match opt(parse_move_number_after_annotation)(input)? {
+        (_, Some(black_move_number)) if black_move_number != move_number => return Err(move_number_failure(black_input, "black's ply numbered as white's")),
+        (input, Some(_)) => map(cut(context("SAN move", parse_pgn_ply)), Some)(input)?,
+        (input, None) => opt(parse_pgn_ply)(input)?,
+    };
+
+    let variations_numbered = variations_numbered_from(&white_ply, (move_number, Color::White))
+        && black_ply.as_ref().is_none_or(| black_ply | variations_numbered_from(black_ply, (move_number, Color::Black)));
+    if !variations_numbered { return Err(move_number_failure(move_input, "variations numbered from the move they replace")); }
+
+    Ok((input, PGNmove { move_number, white_ply: Some(white_ply), black_ply }))
+
+}
+
+// A move where only black's ply is given, as at the start of a game or variation on black's move
+pub fn parse_san_move_black<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNmove, E> {
+    let move_input = input;
+    let (input, (move_number, black_ply)) = pair(parse_move_number_after_annotation, cut(context("SAN move", parse_pgn_ply)))(input)?;
+    if !variations_numbered_from(&black_ply, (move_number, Color::Black)) {
+        return Err(move_number_failure(move_input, "variations numbered from the move they replace"));
+    }
+    Ok((input, PGNmove { move_number, white_ply: None, black_ply: Some(black_ply) }))
 }
 
 pub fn parse_san_game_termination_marker<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNGameTerminationMarker, E> {
@@ -258,8 +351,30 @@ pub fn parse_san_game_termination_marker<'a, E: ParseError<&'a str>>(input: &'a
     ))(input)
 }
 
-pub fn parse_san_movetext<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNmovetext, E> {
-    map(many1(parse_san_move), | val | PGNmovetext {moves: val})(input)
+// Movetext of a game or variation, either of which may start with black's move
+pub fn parse_san_movetext<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNmovetext, E> {
+    let (mut input, leading_comments) = opt(parse_commentry)(input)?;
+    let mut moves: Vec<PGNmove> = Vec::new();
+    if let (remaining, Some(first_move)) = opt(parse_san_move_black)(input)? {
+        moves.push(first_move);
+        input = remaining;
+    }
+
+    // Each move must be numbered on from the one before it
+    loop {
+        match parse_san_move(input) {
+            Ok((remaining, mv)) => {
+                if moves.last().is_some_and(| previous | move_numbering(&mv) != next_move_numbering(previous)) {
+                    return Err(move_number_failure(input, "move numbered in sequence"));
+                }
+                moves.push(mv);
+                input = remaining;
+            },
+            Err(nom::Err::Error(_)) if !moves.is_empty() => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok((input, PGNmovetext { leading_comments: leading_comments.unwrap_or_default(), moves }))
 }
 
 // Parse Tag Pair Date/Times
@@ -371,7 +486,7 @@ pub fn parse_tag_pair_timecontrol<'a, E: ParseError<&'a str>>(input: &'a str) ->
 pub fn parse_tag_value<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<&str>, E> {
     delimited(
         tag("\""),
-        opt(escaped(is_not(r#"\""#), '\\', one_of(r#"""#))),
+        opt(escaped(is_not(r#"\""#), '\\', one_of(r#""\"#))),
         tag("\""),
     )(input)
 }
@@ -385,14 +500,16 @@ pub fn parse_tag_pair<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a
     Ok((input, (tag, value.unwrap_or(""))))
 }
 
-pub fn parse_tag_pairs<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNTagPairRoster, E> {
+pub fn parse_tag_pairs<'a, E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, FenError>>(input: &'a str) -> IResult<&'a str, PGNTagPairRoster, E> {
 
+    // Commentary after the last tag pair is left for the movetext, it precedes the first move
     let (input, tag_pairs) = many1(tuple((
         parse_tag_pair,
-        opt(parse_commentry),
+        opt(terminated(parse_commentry, peek(char('[')))),
     )))(input)?;
 
     let mut tag_pair_roster = PGNTagPairRoster::default();
+    let mut setup = None;
 
     for ((tag, value), _) in tag_pairs {
         match tag {
@@ -406,27 +523,222 @@ pub fn parse_tag_pairs<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'
             },
             "White" => tag_pair_roster.white = Some(value.to_string()),
             "Black" => tag_pair_roster.black = Some(value.to_string()),
-            "Result" => (_, tag_pair_roster.result) = parse_san_game_termination_marker(value)?,
+            "Result" => (_, tag_pair_roster.result) = context("game termination marker", parse_san_game_termination_marker)(value)?,
             "Time" => (_, tag_pair_roster.time) = parse_tag_pair_time::<E>(value).unwrap_or(("", PGNTimeTag{ hour: None, minute: None, second: None })),
-            "TimeControl" => (_, tag_pair_roster.time_control) = parse_tag_pair_timecontrol(value)?,
-            "Setup" | "SetUp" | "setup" => (),
-            "FEN" => tag_pair_roster.fen_string = Some(value.to_string()),
+            "TimeControl" => (_, tag_pair_roster.time_control) = context("time control", parse_tag_pair_timecontrol)(value)?,
+            "Setup" | "SetUp" | "setup" => setup = Some(value),
+            "FEN" => tag_pair_roster.fen = Some(value.parse().map_err(| err: FenError | {
+                nom::Err::Failure(E::from_external_error(&value[err.offset..], ErrorKind::Verify, err))
+            })?),
             _ => tag_pair_roster.other_tag_pairs.push(PGNGenericTagPair{ tag: tag.to_string(), value: value.to_string() }),
         }
     }
+
+    // SetUp "1" must be given with a FEN tag and SetUp "0" without, FEN alone is accepted
+    let setup_failure = | value, expected | nom::Err::Failure(E::add_context(value, expected, E::from_error_kind(value, ErrorKind::Verify)));
+    match (setup, &tag_pair_roster.fen) {
+        (Some(value @ "1"), None) => return Err(setup_failure(value, "FEN tag with SetUp \"1\"")),
+        (Some(value @ "0"), Some(_)) => return Err(setup_failure(value, "SetUp \"1\" with a FEN tag")),
+        (Some(value), _) if value != "0" && value != "1" => return Err(setup_failure(value, "SetUp \"0\" or \"1\"")),
+        _ => (),
+    }
     
     Ok((input, tag_pair_roster ))
 
 }
 
 // Parse whole PGN file
-pub fn parse_pgn_file<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, PGNFile, E> {
-    let (input, (tag_pair_roster, movetext, game_termination_marker)) = tuple((
-        terminated(parse_tag_pairs, opt(parse_commentry)),
-        parse_san_movetext,
-        terminated(parse_san_game_termination_marker, opt(parse_commentry)),
-    ))(input)?;
-    Ok((input, PGNFile{ tag_pair_roster, movetext, game_termination_marker }))
+pub fn parse_pgn_file<'a, E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, FenError>>(input: &'a str) -> IResult<&'a str, PGNFile, E> {
+    let (input, tag_pair_roster) = context("tag pair", parse_tag_pairs)(input)?;
+    let (first_move_input, leading_comments) = opt(parse_commentry::<E>)(input)?;
+
+    // A game that was never played, such as a forfeit, has no moves before its termination marker
+    let (input, movetext) = match peek(parse_san_game_termination_marker::<E>)(first_move_input) {
+        Ok(_) => (first_move_input, PGNmovetext { leading_comments: leading_comments.unwrap_or_default(), moves: Vec::new() }),
+        Err(_) => {
+            let (input, movetext) = context("SAN move", parse_san_movetext)(input)?;
+            if movetext.moves.first().map(move_numbering) != Some(starting_move_numbering(&tag_pair_roster)) {
+                return Err(move_number_failure(first_move_input, "move number of the starting position"));
+            }
+            (input, movetext)
+        },
+    };
+    let (input, game_termination_marker) = terminated(context("game termination marker", parse_san_game_termination_marker), opt(parse_commentry))(input)?;
+    Ok((input, PGNFile{ tag_pair_roster, movetext, game_termination_marker, diagnostics: Vec::new() }))
+}
+
+// Parses a single game, reporting any error by line and column within the input
+pub fn parse_pgn_game(input: &str) -> Result<PGNFile, PgnError> {
+    parse_pgn_file::<PgnParseError>(input)
+        .map(| (_, game) | game)
+        .map_err(| err | PgnError::from_parse_error(input, err))
+}
+
+// Lenient import
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LenientRecovery {
+    ResyncAtNextGame, // Abandon the rest of the game and resume at the next tag pair section
+    SkipToken, // Drop the unparseable token and carry on with the game
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ImportMode {
+    #[default]
+    Strict, // Any malformed token fails the whole game, for validation
+    Lenient(LenientRecovery), // Malformed movetext is recorded as a diagnostic on the game
+}
+
+// Returns the token containing the error, delimited by whitespace or the parentheses of a
+// variation, and the input following it
+fn split_at_bad_token<'a>(input: &'a str, err: &PgnParseError<'a>) -> (&'a str, &'a str) {
+    let is_delimiter = | c: char | c.is_whitespace() || c == '(' || c == ')';
+    let error_offset = offset_in(input, err.input());
+    let token_start = input[..error_offset].rfind(is_delimiter).map(| index | index + 1).unwrap_or(0);
+    let mut token_end = input[error_offset..].find(is_delimiter).map(| index | error_offset + index).unwrap_or(input.len());
+    if token_end == token_start {
+        token_end += input[token_start..].chars().next().map(char::len_utf8).unwrap_or(0);
+    }
+    (&input[token_start..token_end], &input[token_end..])
+}
+
+// How a line of movetext parsed in lenient mode came to an end
+enum LenientEnd {
+    VariationClosed,
+    Terminated(PGNGameTerminationMarker),
+    Unterminated, // The input ran out or the next game's tag pairs were reached
+    Abandoned, // The rest of the game was skipped, the input is at the next game
+}
+
+// The numbering of the ply following one with the numbering given
+fn numbering_after((move_number, color): MoveNumbering) -> MoveNumbering {
+    match color {
+        Color::White => (move_number, Color::Black),
+        Color::Black => (move_number + 1, Color::White),
+    }
+}
+
+// Parses the main line or a variation a token at a time, keeping each ply as it is read so that
+// a malformed token loses only itself rather than the rest of the move it is part of
+fn parse_movetext_lenient<'a>(source: &str, mut input: &'a str, mut numbering: MoveNumbering, recovery: LenientRecovery, in_variation: bool, diagnostics: &mut Vec<PgnDiagnostic>) -> (&'a str, PGNmovetext, LenientEnd) {
+    let mut movetext = PGNmovetext { leading_comments: Vec::new(), moves: Vec::new() };
+    let mut last_numbering = None; // Of the last ply read, the ply a variation replaces
+    let mut ply_expected = false; // A move number has been read but not yet the ply it numbers
+
+    let (input, end) = loop {
+        input = input.trim_start();
+        if input.is_empty() || input.starts_with('[') {
+            if in_variation { diagnostics.push(PgnDiagnostic::at(source, input, "variation not closed".to_string())); }
+            break (input, LenientEnd::Unterminated);
+        }
+        if let Ok((remaining, game_termination_marker)) = terminated(parse_san_game_termination_marker::<PgnParseError>, opt(parse_commentry))(input) {
+            if in_variation { diagnostics.push(PgnDiagnostic::at(source, input, "variation not closed".to_string())); }
+            break (remaining, LenientEnd::Terminated(game_termination_marker));
+        }
+        if let (true, Some(remaining)) = (in_variation, input.strip_prefix(')')) {
+            break (remaining, LenientEnd::VariationClosed);
+        }
+
+        if let Some(remaining) = input.strip_prefix('(') {
+            let (remaining, variation, end) = parse_movetext_lenient(source, remaining, last_numbering.unwrap_or(numbering), recovery, true, diagnostics);
+            // A variation all of whose moves were skipped is dropped
+            if let (Some(ply), false) = (movetext.last_ply_mut(), variation.moves.is_empty()) { ply.variations.push(variation); }
+            input = remaining;
+            match end {
+                LenientEnd::VariationClosed => continue,
+                end => break (input, end),
+            }
+        }
+        if let Ok((remaining, comments)) = parse_commentry::<PgnParseError>(input) {
+            match movetext.last_ply_mut() {
+                Some(ply) => ply.comments.extend(comments),
+                None => movetext.leading_comments.extend(comments),
+            }
+            input = remaining;
+            continue;
+        }
+        if let Ok((remaining, nag)) = parse_nag::<PgnParseError>(input) {
+            match movetext.last_ply_mut() {
+                Some(ply) => ply.nags.push(nag),
+                None => diagnostics.push(PgnDiagnostic::at(source, input, "skipped a NAG before the first move".to_string())),
+            }
+            input = remaining;
+            continue;
+        }
+        if let Ok((remaining, move_numbering)) = alt((
+            map(parse_move_number::<PgnParseError>, | move_number | (move_number, Color::White)),
+            map(parse_move_number_after_annotation, | move_number | (move_number, Color::Black)),
+        ))(input) {
+            // Black's ply may be left out, so white's next move may follow white's ply
+            if move_numbering != numbering && !(numbering.1 == Color::Black && move_numbering == numbering_after(numbering)) {
+                let message = format!("move numbered {} where {} was expected", move_numbering_label(move_numbering), move_numbering_label(numbering));
+                diagnostics.push(PgnDiagnostic::at(source, input, message));
+            }
+            numbering = move_numbering;
+            ply_expected = true;
+            input = remaining;
+            continue;
+        }
+
+        let err = match context("SAN move", parse_san_ply::<PgnParseError>)(input) {
+            Ok((remaining, (san, (check, nags)))) => {
+                if numbering.1 == Color::White && !ply_expected {
+                    diagnostics.push(PgnDiagnostic::at(source, input, format!("missing move number {}", move_numbering_label(numbering))));
+                }
+                movetext.push_ply(numbering.0, numbering.1, PGNply { san, check, nags, comments: Vec::new(), variations: Vec::new() });
+                last_numbering = Some(numbering);
+                numbering = numbering_after(numbering);
+                ply_expected = false;
+                input = remaining;
+                continue;
+            },
+            Err(nom::Err::Error(err) | nom::Err::Failure(err)) => err,
+            Err(nom::Err::Incomplete(_)) => PgnParseError::new(input, "SAN move"),
+        };
+
+        let (token, remaining) = split_at_bad_token(input, &err);
+        let err = PgnError::from_parse_error(source, nom::Err::Failure(err));
+        match recovery {
+            LenientRecovery::ResyncAtNextGame => {
+                diagnostics.push(PgnDiagnostic::from_error(&err, "skipped the rest of the game"));
+                break (find_next_game(input), LenientEnd::Abandoned);
+            },
+            LenientRecovery::SkipToken => {
+                diagnostics.push(PgnDiagnostic::from_error(&err, &format!("skipped '{}'", token)));
+                // A token skipped in place of a numbered ply takes its turn, keeping later plies on the right side
+                if ply_expected {
+                    numbering = numbering_after(numbering);
+                    ply_expected = false;
+                }
+                input = remaining;
+            },
+        }
+    };
+
+    if let (LenientEnd::Unterminated, false) = (&end, in_variation) {
+        diagnostics.push(PgnDiagnostic::at(source, input, "missing game termination marker".to_string()));
+    }
+    (input, movetext, end)
+}
+
+// Parses a game, recording a diagnostic for each malformed movetext token rather than failing.
+// Input must be a slice of source, the whole text being read, so that diagnostics can be located.
+// Errors in the tag pair section cannot be recovered from and are still returned.
+pub fn parse_pgn_file_lenient<'a>(source: &str, input: &'a str, recovery: LenientRecovery) -> Result<(&'a str, PGNFile), PgnError> {
+    let (input, tag_pair_roster) = context("tag pair", parse_tag_pairs::<PgnParseError>)(input)
+        .map_err(| err | PgnError::from_parse_error(source, err))?;
+
+    let mut diagnostics = Vec::new();
+    let (remaining, movetext, end) = parse_movetext_lenient(source, input, starting_move_numbering(&tag_pair_roster), recovery, false, &mut diagnostics);
+    let game_termination_marker = match end {
+        LenientEnd::Terminated(game_termination_marker) => game_termination_marker,
+        _ => PGNGameTerminationMarker::Undetermined,
+    };
+    Ok((remaining, PGNFile { tag_pair_roster, movetext, game_termination_marker, diagnostics }))
+}
+
+pub fn parse_pgn_game_lenient(input: &str, recovery: LenientRecovery) -> Result<PGNFile, PgnError> {
+    parse_pgn_file_lenient(input, input, recovery).map(| (_, game) | game)
 }
 
 #[cfg(test)]
@@ -459,7 +771,115 @@ mod tests {
         import_movetext_test(movetext_input, movetext_expected_output);
 
     }
+
+    #[test]
+    fn import_variation_tests() {
+
+        // Nested variations, including lines starting with black's move
+        let movetext_input = "1. e4 e5 (1... c5 2. Nf3 (2. c3 d5 (2... Nf6 3. e5)) 2... d6 {Najdorf territory}) 2. Nf3 (2. f4 exf4) 2... Nc6 (2... d6 3. d4 (3. Bc4)) 3. Bb5";
+        let movetext_expected_output = "1. e4 e5 (1... c5 2. Nf3 (2. c3 d5 (2... Nf6 3. e5)) 2... d6 {Najdorf territory}) 2. Nf3 (2. f4 exf4) 2... Nc6 (2... d6 3. d4 (3. Bc4)) 3. Bb5 ";
+        import_movetext_test(movetext_input, movetext_expected_output);
+
+        let (_, output) = parse_san_movetext::<nom::error::Error<_>>(movetext_input).unwrap();
+        let black_ply = output.moves[0].black_ply.as_ref().unwrap();
+        assert_eq!(black_ply.variations.len(), 1);
+        assert!(black_ply.variations[0].moves[0].white_ply.is_none());
+        assert_eq!(black_ply.variations[0].moves[1].white_ply.as_ref().unwrap().variations[0].moves[0].black_ply.as_ref().unwrap().variations.len(), 1);
+
+        assert!(parse_san_movetext::<nom::error::Error<_>>("1. e4 (1. d4 d5 e5").unwrap().0.starts_with("(1. d4"));
+
+    }
+
+    #[test]
+    fn import_comment_tests() {
+
+        let movetext_input = "{Before the first move} 1. e4 ; King's pawn
+        e5 {Symmetrical} {Two comments} 2. Nf3 ({Alternatively} 2. Bc4 {Bishop's opening}) Nc6 {After the last move}";
+        let movetext_expected_output = "{Before the first move} 1. e4 { King's pawn} 1... e5 {Symmetrical} {Two comments} 2. Nf3 ({Alternatively} 2. Bc4 {Bishop's opening}) 2... Nc6 {After the last move} ";
+        import_movetext_test(movetext_input, movetext_expected_output);
+
+        let (_, output) = parse_san_movetext::<nom::error::Error<_>>(movetext_input).unwrap();
+        assert_eq!(output.leading_comments, vec!["Before the first move".to_string()]);
+        assert_eq!(output.moves[0].black_ply.as_ref().unwrap().comments.len(), 2);
+
+        // Comments survive a round trip
+        let (_, reimported) = parse_san_movetext::<nom::error::Error<_>>(movetext_expected_output).unwrap();
+        assert_eq!(reimported.to_string(), movetext_expected_output);
+
+        // NAGs may follow the comments and variations of a ply as well as precede them
+        let game = parse_pgn_game("[Event \"?\"]\n\n1. e4 {c} $1 (1. d4) $14 e5 *").unwrap();
+        assert_eq!(game.moves()[0].white_ply().unwrap().nags(), [Nag::GOOD_MOVE, Nag(14)]);
+        assert!(game.to_string().ends_with("\n1. e4! $14 {c} (1. d4) 1... e5 *"));
+
+    }
     
+    #[test]
+    fn import_fen_tag_tests() {
+
+        let game = parse_pgn_game("[SetUp \"1\"]\n[FEN \"8/8/4k3/8/2pP4/8/8/4K3 b - d3 0 57\"]\n\n57... cxd3 *").unwrap();
+        assert_eq!(game.starting_position().fullmove_number(), 57);
+        assert!(game.to_string().contains("[FEN \"8/8/4k3/8/2pP4/8/8/4K3 b - d3 0 57\"]\n"));
+
+        let err = parse_pgn_game("[SetUp \"1\"]\n[FEN \"8/8/4k3/8/2pP4/8/8/4K3 b - d4 0 57\"]\n\n57... Kd5 *").unwrap_err();
+        assert_eq!(err.to_string(), "invalid FEN, en passant square is not behind a pawn that has just moved two squares at 2:34");
+        let err = parse_pgn_game("[SetUp \"1\"]\n\n1. e4 *").unwrap_err();
+        assert_eq!(err.to_string(), "expected FEN tag with SetUp \"1\", found '1' at 1:9");
+
+    }
+
+    #[test]
+    fn import_move_number_tests() {
+
+        let input = "[Event \"Black to move\"]\n[FEN \"rnbqkbnr/pppp1ppp/8/8/8/8/PPP2PPP/RN1Q1RK1 b kq - 0 23\"]\n\n{From the adjourned position} 23... Nf6 24. Re1 Nd5 (24... Ne4 25. Rxe4) 25. Qd2 1/2-1/2";
+
+        let game = parse_pgn_game(input).unwrap();
+        assert_eq!(game.movetext.moves[0].move_number, 23);
+        assert!(game.movetext.moves[0].white_ply.is_none());
+        assert!(game.to_string().ends_with("\n{From the adjourned position} 23... Nf6 24. Re1 Nd5 (24... Ne4 25. Rxe4) 25. Qd2 1/2-1/2"));
+
+        let err = parse_pgn_game("[Event \"No FEN\"]\n\n23... Nf6 24. Re1 *").unwrap_err();
+        assert_eq!(err.to_string(), "expected move number of the starting position, found '23...' at 3:1");
+        let err = parse_pgn_game("[Event \"Skipped\"]\n\n1. e4 e5 2. Nf3 Nc6 4. Bb5 *").unwrap_err();
+        assert_eq!(err.to_string(), "expected move numbered in sequence, found '4.' at 3:21");
+        let err = parse_pgn_game("[Event \"Variation\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 (4. Bc4) *").unwrap_err();
+        assert_eq!(err.to_string(), "expected variations numbered from the move they replace, found '3.' at 3:21");
+
+        let game = parse_pgn_game_lenient("[Event \"Skipped\"]\n\n1. e4 e5 2. Nf3 Nc6 4. Bb5 *", LenientRecovery::SkipToken).unwrap();
+        assert!(game.to_string().ends_with("\n1. e4 e5 2. Nf3 Nc6 4. Bb5 *"));
+        assert_eq!(game.diagnostics()[0].to_string(), "move numbered 4. where 3. was expected at 3:21");
+
+    }
+
+    #[test]
+    fn import_lenient_tests() {
+
+        let input = "[Event \"Lenient\"]\n\n1. e4 e5 2. Nz9 Nc6 3. Bb5 a6% 4. Ba4 Nf6 *";
+
+        assert!(parse_pgn_game(input).is_err());
+
+        let game = parse_pgn_game_lenient(input, LenientRecovery::SkipToken).unwrap();
+        assert!(game.to_string().ends_with("\n1. e4 e5 2... Nc6 3. Bb5 a6 4. Ba4 Nf6 *"));
+        assert_eq!(game.diagnostics().len(), 2);
+        assert_eq!(game.diagnostics()[0].to_string(), "expected square after 'N', found 'z9'; skipped 'Nz9' at 3:14");
+
+        let game = parse_pgn_game_lenient(input, LenientRecovery::ResyncAtNextGame).unwrap();
+        assert!(game.to_string().ends_with("\n1. e4 e5 *"));
+        assert_eq!(game.diagnostics()[0].message(), "expected square after 'N', found 'z9'; skipped the rest of the game");
+
+        // Only the bad ply is lost, not the rest of the move or the variation it is in
+        let game = parse_pgn_game_lenient("[Event \"Lenient\"]\n\n1. e4 e5 2. Nf3 2... Nz9 3. Bb5 a6 *", LenientRecovery::SkipToken).unwrap();
+        assert!(game.to_string().ends_with("\n1. e4 e5 2. Nf3 3. Bb5 a6 *"));
+        assert_eq!(game.diagnostics().len(), 1);
+        let game = parse_pgn_game_lenient("[Event \"Lenient\"]\n\n1. e4 e5 2. Nf3 (2. Nz9) Nc6 (2... Nz9 3. Bc4) 3. Bb5 *", LenientRecovery::SkipToken).unwrap();
+        assert!(game.to_string().ends_with("\n1. e4 e5 2. Nf3 Nc6 (3. Bc4) 3. Bb5 *"));
+        assert_eq!(game.diagnostics().len(), 2);
+
+        let game = parse_pgn_game_lenient("[Event \"No Result\"]\n\n1. e4 e5 2. Nf3", LenientRecovery::SkipToken).unwrap();
+        assert!(game.to_string().ends_with("\n1. e4 e5 2. Nf3 *"));
+        assert_eq!(game.diagnostics()[0].to_string(), "missing game termination marker at 3:16");
+
+    }
+
     fn import_pgn_test(input: &str, expected_output: &str) {
         let (_, output) = parse_pgn_file::<nom::error::Error<_>>(input).unwrap();
         assert_eq!(output.to_string(), expected_output);
@@ -485,17 +905,17 @@ mod tests {
         hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5{Another Random Comment.}
         35. Ra7 g6 36. Ra6+ Kc5 37. Ke1{Another Random Comment.} 37... Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
         Nf2 42. g4 Bd3 43. Re6# {Another Random Comment.}1/2-1/2";
-        let expected_output = "[Event \"F/S Return Match\"]\n[Site \"Belgrade, Serbia JUG\"]\n[Date \"1992.11.04\"]\n[Round \"29\"]\n[White \"Fischer, Robert J.\"]\n[Black \"Spassky, Boris V.\"]\n[Result \"1/2-1/2\"]\n[Time \"??:??:??\"]\n[TimeControl \"?\"]\n[Setup \"0\"]\n\n1. e4!! e5!? 2. Nf3?! Nc6?? 3. Bb5$120 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5 Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42. g4 Bd3 43. Re6# 1/2-1/2";
+        let expected_output = "[Event \"F/S Return Match\"]\n[Site \"Belgrade, Serbia JUG\"]\n[Date \"1992.11.04\"]\n[Round \"29\"]\n[White \"Fischer, Robert J.\"]\n[Black \"Spassky, Boris V.\"]\n[Result \"1/2-1/2\"]\n[Time \"??:??:??\"]\n[TimeControl \"?\"]\n[Setup \"0\"]\n\n1. e4!! e5!? 2. Nf3?! Nc6?? 3. Bb5 $120 a6 {This opening is called the Ruy Lopez.} 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 {Another Random Comment.} 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 (14. Bg5 b4 15. Nb1 h6) 14... b4 15. Nb1 h6 16. Bh4 c5 17. dxe5 Nxe4 18. Bxe7 Qxe7 19. exd6 {Another Random Comment.} 19... Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 {Another Random Comment.} 35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 {Another Random Comment.} 37... Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42. g4 Bd3 43. Re6# {Another Random Comment.} 1/2-1/2";
         import_pgn_test(input, expected_output);
 
         // Chess 960 Game PGN
         let input = "[Event \"Chess960: 2005 Fischer Random Dropout Tournament, Round 6\"] [Site \"SchemingMind.com\"] [Time \"14:06:56\"] [Date \"????.??.??\"] [Round \"-\"] [White \"gvhill\"] [Black \"saxon\"] [Result \"1-0\"] [Variant \"fischerandom\"] [SetUp \"1\"] [FEN \"rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w KQkq - 0 1\"] [WhiteCountry \"USA\"] [BlackCountry \"GER\"] [TimeControl \"123+456\"] 1. d4 { Congratulations on making the final round!  It's a pleasure to play you again. } 1... d5 2. Nf3 Nf6 3. Ne3 Ne6 4. c4 dxc4 5. Nxc4 c5 6. dxc5 Qd7+ 7. Qd2 Nxc5 8. Qxd7+ Bxd7 9. O-O O-O 10. Rd1 { I think we have a draw from here.  What do you think? } { looks like a very drawish position. After another 10 move we will end in an equal endgame. Not much excitment here.... } 1-0";
-        let expected_output = "[Event \"Chess960: 2005 Fischer Random Dropout Tournament, Round 6\"]\n[Site \"SchemingMind.com\"]\n[Date \"????.??.??\"]\n[Round \"-\"]\n[White \"gvhill\"]\n[Black \"saxon\"]\n[Result \"1-0\"]\n[Time \"14:06:56\"]\n[TimeControl \"123+456\"]\n[Setup \"1\"]\n[FEN \"rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w KQkq - 0 1\"]\n[Variant \"fischerandom\"]\n[WhiteCountry \"USA\"]\n[BlackCountry \"GER\"]\n\n1. d4 d5 2. Nf3 Nf6 3. Ne3 Ne6 4. c4 dxc4 5. Nxc4 c5 6. dxc5 Qd7+ 7. Qd2 Nxc5 8. Qxd7+ Bxd7 9. O-O O-O 10. Rd1 1-0";
+        let expected_output = "[Event \"Chess960: 2005 Fischer Random Dropout Tournament, Round 6\"]\n[Site \"SchemingMind.com\"]\n[Date \"????.??.??\"]\n[Round \"-\"]\n[White \"gvhill\"]\n[Black \"saxon\"]\n[Result \"1-0\"]\n[Time \"14:06:56\"]\n[TimeControl \"123+456\"]\n[Setup \"1\"]\n[FEN \"rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w KQkq - 0 1\"]\n[Variant \"fischerandom\"]\n[WhiteCountry \"USA\"]\n[BlackCountry \"GER\"]\n\n1. d4 { Congratulations on making the final round!  It's a pleasure to play you again. } 1... d5 2. Nf3 Nf6 3. Ne3 Ne6 4. c4 dxc4 5. Nxc4 c5 6. dxc5 Qd7+ 7. Qd2 Nxc5 8. Qxd7+ Bxd7 9. O-O O-O 10. Rd1 { I think we have a draw from here.  What do you think? } { looks like a very drawish position. After another 10 move we will end in an equal endgame. Not much excitment here.... } 1-0";
         import_pgn_test(input, expected_output);
 
         // Additional tests to mop up any remaining untested paths
         let input = "[Date \"1992.11.04\"]\n[Round \"?\"]\n[Result \"*\"]\n[Time \"??:??:??\"]\n[TimeControl \"23/45:10/10+12{delay}:255/456+123{Bronstien type delay}:*100\"]\n[Setup \"0\"]\n\n1. e4!! e5!? 2. Nf3?! Nc6?? 3. Bb5$120 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. 1c3 O-O 9. ah3 Nb8 10. d4 Nbd7 11. c4 hc6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 4h6 16. Bh4 c5 17. dxe5 Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42. g4 Bd3 43. Re6# 0-1";
-        let expected_output = "[Event \"?\"]\n[Site \"?\"]\n[Date \"1992.11.04\"]\n[Round \"?\"]\n[White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n[Time \"??:??:??\"]\n[TimeControl \"23/45:10/10+12{delay}:255/456+123{Bronstien type delay}:*100\"]\n[Setup \"0\"]\n\n1. e4!! e5!? 2. Nf3?! Nc6?? 3. Bb5$120 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. 1c3 O-O 9. ah3 Nb8 10. d4 Nbd7 11. c4 hc6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 4h6 16. Bh4 c5 17. dxe5 Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42. g4 Bd3 43. Re6# 0-1";
+        let expected_output = "[Event \"?\"]\n[Site \"?\"]\n[Date \"1992.11.04\"]\n[Round \"?\"]\n[White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n[Time \"??:??:??\"]\n[TimeControl \"23/45:10/10+12{delay}:255/456+123{Bronstien type delay}:*100\"]\n[Setup \"0\"]\n\n1. e4!! e5!? 2. Nf3?! Nc6?? 3. Bb5 $120 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. 1c3 O-O 9. ah3 Nb8 10. d4 Nbd7 11. c4 hc6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 4h6 16. Bh4 c5 17. dxe5 Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42. g4 Bd3 43. Re6# 0-1";
         import_pgn_test(input, expected_output);
 
         let input = "[Event \"3rd Al Ain Chess Rapid\"]
@@ -0,0 +1,298 @@
+use crate::definitions::*;
+use crate::error::*;
+use crate::position::*;
+use crate::position::movegen::*;
+
+use super::*;
+
+use std::collections::HashMap;
+
+// Draws a player may claim, and the fivefold repetition, seventy-five move and insufficient
+// material rules that end the game without a claim
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawRule {
+    ThreefoldRepetition,
+    FiftyMoves,
+    FivefoldRepetition,
+    SeventyFiveMoves,
+    InsufficientMaterial,
+}
+
+impl DrawRule {
+    pub fn is_automatic(self) -> bool {
+        matches!(self, DrawRule::FivefoldRepetition | DrawRule::SeventyFiveMoves | DrawRule::InsufficientMaterial)
+    }
+}
+
+// A ply of the mainline as played, along with the position it led to. The check is worked out
+// from that position, the written check is the + or # suffix given in the movetext.
+#[derive(Debug, Clone)]
+pub struct ReplayedPly {
+    pub(crate) move_number: u32,
+    pub(crate) color: Color,
+    pub(crate) mv: Move,
+    pub(crate) position: Position,
+    pub(crate) zobrist_hash: u64,
+    pub(crate) repetitions: u32, // Times the position has been reached in the game, this time included
+    pub(crate) check: Option<CheckType>,
+    pub(crate) written_check: Option<CheckType>,
+}
+
+impl ReplayedPly {
+    pub fn move_number(&self) -> u32 {
+        self.move_number
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn mv(&self) -> &Move {
+        &self.mv
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn zobrist_hash(&self) -> u64 {
+        self.zobrist_hash
+    }
+
+    pub fn repetitions(&self) -> u32 {
+        self.repetitions
+    }
+
+    pub fn check(&self) -> Option<CheckType> {
+        self.check
+    }
+
+    pub fn written_check(&self) -> Option<CheckType> {
+        self.written_check
+    }
+
+    // Stalemate has no suffix, so is only a mismatch if the ply was written with + or #
+    pub fn check_suffix_matches(&self) -> bool {
+        self.written_check == self.check.filter(| check | *check != CheckType::StaleMate)
+    }
+
+    // The draw rules that apply in the position reached. A move that checkmates is not drawn by
+    // the seventy-five move rule.
+    pub fn draw_rules(&self) -> Vec<DrawRule> {
+        let halfmove_clock = self.position.halfmove_clock();
+        [
+            (DrawRule::ThreefoldRepetition, self.repetitions >= 3),
+            (DrawRule::FiftyMoves, halfmove_clock >= 100),
+            (DrawRule::FivefoldRepetition, self.repetitions >= 5),
+            (DrawRule::SeventyFiveMoves, halfmove_clock >= 150 && self.check != Some(CheckType::CheckMate)),
+            (DrawRule::InsufficientMaterial, self.position.is_insufficient_material()),
+        ].into_iter().filter_map(| (rule, applies) | applies.then_some(rule)).collect()
+    }
+}
+
+// The mainline of a game played out from its starting position
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub(crate) starting_position: Position,
+    pub(crate) plies: Vec<ReplayedPly>,
+}
+
+impl Replay {
+    pub fn starting_position(&self) -> &Position {
+        &self.starting_position
+    }
+
+    pub fn plies(&self) -> &[ReplayedPly] {
+        &self.plies
+    }
+
+    pub fn final_position(&self) -> &Position {
+        self.plies.last().map(| ply | &ply.position).unwrap_or(&self.starting_position)
+    }
+
+    // Plies whose + or # suffix does not match the position they led to
+    pub fn check_mismatches(&self) -> impl Iterator<Item = &ReplayedPly> {
+        self.plies.iter().filter(| ply | !ply.check_suffix_matches())
+    }
+
+    // The first ply after which either player could have claimed a draw
+    pub fn first_claimable_draw(&self) -> Option<(&ReplayedPly, DrawRule)> {
+        self.plies.iter().find_map(| ply | ply.draw_rules().first().map(| &rule | (ply, rule)))
+    }
+
+    // The ply after which the game was drawn without a claim, any plies after it should not
+    // have been played
+    pub fn automatic_draw(&self) -> Option<(&ReplayedPly, DrawRule)> {
+        self.plies.iter().find_map(| ply | ply.draw_rules().into_iter().find(| rule | rule.is_automatic()).map(| rule | (ply, rule)))
+    }
+}
+
+// Each ply of the movetext with the side that plays it, in the order they are played
+pub(crate) fn movetext_plies(movetext: &PGNmovetext) -> impl Iterator<Item = (u32, Color, &PGNply)> {
+    movetext.moves.iter().flat_map(| mv | {
+        [(Color::White, &mv.white_ply), (Color::Black, &mv.black_ply)].into_iter()
+            .filter_map(move | (color, ply) | ply.as_ref().map(| ply | (mv.move_number, color, ply)))
+    })
+}
+
+fn play_ply(position: &Position, move_number: u32, color: Color, ply: &PGNply, in_variation: bool) -> Result<(Move, Position), ReplayError> {
+    let replay_error = | error | ReplayError { move_number, color, in_variation, error };
+    if color != position.side_to_move() { return Err(replay_error(SanError::NoLegalMove(ply.san.to_string()))); }
+    let mv = position.resolve_san(&ply.san).map_err(replay_error)?;
+    Ok((mv, position.play(&mv)))
+}
+
+// Plays the movetext from the position, adding each ply played to plies. Variations are played
+// from the position before the ply they replace when check_variations is set.
+fn replay_movetext(position: &Position, movetext: &PGNmovetext, check_variations: bool, in_variation: bool, plies: &mut Vec<ReplayedPly>) -> Result<(), ReplayError> {
+    let mut position = position.clone();
+    for (move_number, color, ply) in movetext_plies(movetext) {
+        if check_variations {
+            for variation in &ply.variations {
+                replay_movetext(&position, variation, true, true, &mut Vec::new())?;
+            }
+        }
+
+        let mv;
+        (mv, position) = play_ply(&position, move_number, color, ply, in_variation)?;
+        plies.push(ReplayedPly { move_number, color, mv, zobrist_hash: position.zobrist_hash(), repetitions: 1, check: position.check_type(), written_check: ply.check, position: position.clone() });
+    }
+    Ok(())
+}
+
+// Sets the check of every ply in the movetext and its variations from the position it leads to
+fn regenerate_movetext_checks(position: &Position, movetext: &mut PGNmovetext, in_variation: bool) -> Result<(), ReplayError> {
+    let mut position = position.clone();
+    for mv in &mut movetext.moves {
+        let move_number = mv.move_number;
+        for (color, ply) in [(Color::White, &mut mv.white_ply), (Color::Black, &mut mv.black_ply)] {
+            let Some(ply) = ply else { continue };
+            for variation in &mut ply.variations {
+                regenerate_movetext_checks(&position, variation, true)?;
+            }
+            (_, position) = play_ply(&position, move_number, color, ply, in_variation)?;
+            ply.check = position.check_type().filter(| check | *check != CheckType::StaleMate);
+        }
+    }
+    Ok(())
+}
+
+impl PGNFile {
+    // Plays the mainline from the starting position, stopping at the first ply that is illegal
+    // or ambiguous. Positions are counted as repeated when their Polyglot hashes match.
+    pub fn replay(&self) -> Result<Replay, ReplayError> {
+        let starting_position = self.starting_position();
+        let mut plies = Vec::new();
+        replay_movetext(&starting_position, &self.movetext, false, false, &mut plies)?;
+
+        let mut occurrences = HashMap::from([(starting_position.zobrist_hash(), 1)]);
+        for ply in &mut plies {
+            let count = occurrences.entry(ply.zobrist_hash).or_insert(0);
+            *count += 1;
+            ply.repetitions = *count;
+        }
+        Ok(Replay { starting_position, plies })
+    }
+
+    // Checks that every ply of the game, including those in variations, can be played
+    pub fn validate(&self) -> Result<(), ReplayError> {
+        replay_movetext(&self.starting_position(), &self.movetext, true, false, &mut Vec::new())
+    }
+
+    // Replaces the + and # suffixes written in the movetext with those of the positions reached,
+    // so that they are correct when the game is exported
+    pub fn regenerate_check_suffixes(&mut self) -> Result<(), ReplayError> {
+        regenerate_movetext_checks(&self.starting_position(), &mut self.movetext, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::pgn::pgn_import::*;
+
+    #[test]
+    fn replay_tests() {
+
+        let game = parse_pgn_game("[Event \"?\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 (3... Nf6 4. O-O) 4. Ba4 Nf6 5. O-O *").unwrap();
+        let replay = game.replay().unwrap();
+        assert_eq!(replay.plies().len(), 9);
+        assert_eq!(replay.plies()[8].mv().castle(), Some(Castle::KingSide));
+        assert_eq!(replay.final_position().to_string(), "r1bqkb1r/1ppp1ppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 3 5");
+        assert!(game.validate().is_ok());
+
+        // The variation replaces black's third move, so there is nothing on a6 to capture
+        let game = parse_pgn_game("[Event \"?\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 (3... Nf6 4. Bxa6) 4. Bxc6 *").unwrap();
+        assert!(game.replay().is_ok());
+        assert_eq!(game.validate().unwrap_err().to_string(), "no legal move matches 'Bxa6' at move 4 for white in a variation");
+
+        let game = parse_pgn_game("[Event \"?\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. 1c3 O-O 9. ah3 *").unwrap();
+        let err = game.validate().unwrap_err();
+        assert_eq!((err.move_number(), err.color()), (8, Color::White));
+        assert_eq!(err.to_string(), "no legal move matches '1c3' at move 8 for white");
+
+        // Checkmate written as check, and a check that was never given
+        let mut game = parse_pgn_game("[Event \"?\"]\n\n1. e4 e5 2. Bc4+ Nc6 3. Qh5 Nf6 (3... g6 4. Qf3) 4. Qxf7+ 1-0").unwrap();
+        let replay = game.replay().unwrap();
+        let mismatches: Vec<_> = replay.check_mismatches().map(| ply | (ply.move_number(), ply.written_check(), ply.check())).collect();
+        assert_eq!(mismatches, vec![(2, Some(CheckType::Check), None), (4, Some(CheckType::Check), Some(CheckType::CheckMate))]);
+        game.regenerate_check_suffixes().unwrap();
+        assert!(game.to_string().ends_with("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 (3... g6 4. Qf3) 4. Qxf7# 1-0"));
+
+        // Stalemate has no suffix to write
+        let mut game = parse_pgn_game("[SetUp \"1\"] [FEN \"7k/8/6K1/8/8/8/8/5Q2 w - - 0 1\"] 1. Qf7 1/2-1/2").unwrap();
+        game.regenerate_check_suffixes().unwrap();
+        assert_eq!(game.moves()[0].white_ply().unwrap().check(), None);
+
+        // Chess960 castling, king and rooks starting on d1, a1 and h1
+        let game = parse_pgn_game("[SetUp \"1\"] [FEN \"rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w KQkq - 0 1\"] 1. d4 d5 2. Nf3 Nf6 3. Ne3 Ne6 4. c4 dxc4 5. Nxc4 c5 6. dxc5 Qd7+ 7. Qd2 Nxc5 8. Qxd7+ Bxd7 9. O-O O-O 10. Rd1 1-0").unwrap();
+        assert_eq!(game.replay().unwrap().final_position().to_string(), "rb3rk1/pp1bpppp/5n2/2n5/2N5/5N2/PP2PPPP/RBBR2K1 b - - 3 10");
+        assert_eq!(game.replay().unwrap().first_claimable_draw().map(| (ply, rule) | (ply.move_number(), rule)), None);
+
+        // The Variant tag makes a game from the standard array Chess960, castling with the king
+        // taking its rook in UCI. With an X-FEN right the king castles with the inner rook.
+        let castle_uci = | pgn: &str | {
+            let game = parse_pgn_game(pgn).unwrap();
+            let replay = game.replay().unwrap();
+            let ply = replay.plies().last().unwrap();
+            let position = replay.plies().iter().rev().nth(1).map(| ply | ply.position()).unwrap_or(replay.starting_position());
+            (game.variant(), position.uci(ply.mv()), ply.position().to_string())
+        };
+        let moves = "1. e4 e5 2. Nf3 Nf6 3. Bc4 Bc5 4. O-O *";
+        assert_eq!(castle_uci(&format!("[Variant \"Chess960\"] {}", moves)).0, Variant::Chess960);
+        assert_eq!(castle_uci(&format!("[Variant \"Chess960\"] {}", moves)).1, "e1h1");
+        assert_eq!(castle_uci(&format!("[Variant \"Standard\"] {}", moves)).1, "e1g1");
+        let (variant, uci, position) = castle_uci("[SetUp \"1\"] [FEN \"4k3/8/8/8/8/8/8/R3KR1R w FA - 0 1\"] 1. O-O *");
+        assert_eq!((variant, uci.as_str(), position.as_str()), (Variant::Standard, "e1f1", "4k3/8/8/8/8/8/8/R4RKR b - - 1 1"));
+
+    }
+
+    #[test]
+    fn draw_rule_tests() {
+        // The knights return home twice, the starting position then occurring a third time
+        let shuffle = "1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8";
+        let game = parse_pgn_game(&format!("[Event \"?\"] {} 5. Nf3 Nf6 6. Ng1 Ng8 7. Nf3 Nf6 8. Ng1 Ng8 1/2-1/2", shuffle)).unwrap();
+        let replay = game.replay().unwrap();
+        assert_eq!(replay.plies()[3].zobrist_hash(), replay.starting_position().zobrist_hash());
+        let (ply, rule) = replay.first_claimable_draw().unwrap();
+        assert_eq!((ply.move_number(), ply.color(), ply.repetitions(), rule), (4, Color::Black, 3, DrawRule::ThreefoldRepetition));
+        let (ply, rule) = replay.automatic_draw().unwrap();
+        assert_eq!((ply.move_number(), ply.color(), rule), (8, Color::Black, DrawRule::FivefoldRepetition));
+
+        // Fifty moves without a capture or pawn move, counted on from the FEN's halfmove clock
+        let game = parse_pgn_game("[SetUp \"1\"] [FEN \"4k3/8/8/8/8/8/8/R3K3 w - - 98 80\"] 80. Ra2 Kd8 81. Ra1 *").unwrap();
+        let replay = game.replay().unwrap();
+        let (ply, rule) = replay.first_claimable_draw().unwrap();
+        assert_eq!((ply.move_number(), ply.color(), rule), (80, Color::Black, DrawRule::FiftyMoves));
+        assert_eq!(replay.automatic_draw().map(| (_, rule) | rule), None);
+
+        // Taking the last pawn leaves a king and knight against a king
+        let game = parse_pgn_game("[SetUp \"1\"] [FEN \"8/8/4k3/3p4/8/4N3/8/4K3 w - - 0 1\"] 1. Nxd5 Kxd5 1/2-1/2").unwrap();
+        let replay = game.replay().unwrap();
+        let (ply, rule) = replay.automatic_draw().unwrap();
+        assert_eq!((ply.move_number(), ply.color(), rule), (1, Color::White, DrawRule::InsufficientMaterial));
+
+    }
+
+}
@@ -0,0 +1,84 @@
+use crate::definitions::*;
+use crate::error::*;
+use crate::position::*;
+
+use super::*;
+
+// Checkmate loses for the side to move, and stalemate or a position where neither side has the
+// material to mate is drawn. Otherwise the game could go on.
+fn position_result(position: &Position) -> Option<PGNGameTerminationMarker> {
+    match (position.check_type(), position.side_to_move()) {
+        (Some(CheckType::CheckMate), Color::White) => Some(PGNGameTerminationMarker::BlackWins),
+        (Some(CheckType::CheckMate), Color::Black) => Some(PGNGameTerminationMarker::WhiteWins),
+        (Some(CheckType::StaleMate), _) => Some(PGNGameTerminationMarker::Draw),
+        _ if position.is_insufficient_material() => Some(PGNGameTerminationMarker::Draw),
+        _ => None,
+    }
+}
+
+impl PGNFile {
+    // The result decided by the final position of the mainline, if the game could not go on
+    pub fn adjudicated_result(&self) -> Result<Option<PGNGameTerminationMarker>, ReplayError> {
+        Ok(position_result(self.replay()?.final_position()))
+    }
+
+    // Checks that the Result tag agrees with the game termination marker, and that both agree
+    // with the final position where it decides the game
+    pub fn validate_result(&self) -> Result<(), ResultError> {
+        let (tag, marker) = (self.tag_pair_roster.result, self.game_termination_marker);
+        if tag != marker { return Err(ResultError::TagMismatch { tag, marker }); }
+        match self.adjudicated_result()? {
+            Some(adjudicated) if adjudicated != marker => Err(ResultError::PositionMismatch { marker, adjudicated }),
+            _ => Ok(()),
+        }
+    }
+
+    // Fills in the Result tag and termination marker of a game left as * whose final position
+    // decides it, returning the result filled in
+    pub fn fill_in_result(&mut self) -> Result<Option<PGNGameTerminationMarker>, ReplayError> {
+        let undetermined = PGNGameTerminationMarker::Undetermined;
+        if self.tag_pair_roster.result != undetermined || self.game_termination_marker != undetermined { return Ok(None); }
+        let adjudicated = self.adjudicated_result()?;
+        if let Some(result) = adjudicated {
+            self.tag_pair_roster.result = result;
+            self.game_termination_marker = result;
+        }
+        Ok(adjudicated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::pgn::pgn_import::*;
+
+    #[test]
+    fn result_tests() {
+        let game = parse_pgn_game("[Result \"1/2-1/2\"] 1. e4 e5 0-1").unwrap();
+        assert_eq!(game.validate_result().unwrap_err().to_string(), "Result tag \"1/2-1/2\" does not match the game termination marker 0-1");
+
+        let game = parse_pgn_game("[Result \"1-0\"] 1. f3 e5 2. g4 Qh4# 1-0").unwrap();
+        assert_eq!(game.validate_result().unwrap_err().to_string(), "game termination marker 1-0 does not match the final position, which decides 0-1");
+
+        let game = parse_pgn_game("[Result \"1-0\"] [SetUp \"1\"] [FEN \"k7/8/1Q6/8/8/8/8/7K w - - 0 1\"] 1. Qc7 1-0").unwrap();
+        assert_eq!(game.adjudicated_result().unwrap(), Some(PGNGameTerminationMarker::Draw));
+        assert!(game.validate_result().is_err());
+
+        // A game left unfinished is filled in from its final position
+        let mut game = parse_pgn_game("[Result \"*\"] 1. f3 e5 2. g4 Qh4# *").unwrap();
+        assert!(game.validate_result().is_err());
+        assert_eq!(game.fill_in_result().unwrap(), Some(PGNGameTerminationMarker::BlackWins));
+        assert!(game.validate_result().is_ok());
+        assert!(game.to_string().contains("[Result \"0-1\"]"));
+        assert!(game.to_string().ends_with("2. g4 Qh4# 0-1"));
+
+        let game = parse_pgn_game("[Result \"0-1\"] [SetUp \"1\"] [FEN \"8/8/4k3/3p4/8/4N3/8/4K3 w - - 0 1\"] 1. Nxd5 Kxd5 0-1").unwrap();
+        assert_eq!(game.validate_result().unwrap_err().to_string(), "game termination marker 0-1 does not match the final position, which decides 1/2-1/2");
+
+        let mut game = parse_pgn_game("[Result \"*\"] 1. e4 e5 *").unwrap();
+        assert_eq!(game.fill_in_result().unwrap(), None);
+        assert!(game.validate_result().is_ok());
+    }
+
+}
@@ -0,0 +1,57 @@
+use crate::definitions::*;
+use crate::error::*;
+use crate::position::*;
+
+use super::*;
+use super::pgn_builder::*;
+
+// Builds a game from a whitespace separated list of UCI moves, such as an engine's principal
+// variation, played from the starting position given or the standard one. Each move is written
+// as SAN with its check suffix, and a game ending in checkmate or stalemate is given its result.
+// A Chess960 starting position is recorded in the Variant tag.
+pub fn parse_uci_movelist(input: &str, starting_position: Option<Position>) -> Result<PGNFile, PgnError> {
+    let mut builder = match starting_position {
+        Some(position) => GameBuilder::from_position(position),
+        None => GameBuilder::new(),
+    };
+
+    for uci in input.split_ascii_whitespace() {
+        builder.push_uci(uci).map_err(| err | {
+            let (line, column) = line_and_column(input, offset_in(input, uci));
+            PgnError { kind: PgnErrorKind::Uci(err), line, column, game: None }
+        })?;
+    }
+
+    let position = builder.position();
+    match (position.check_type(), position.side_to_move()) {
+        (Some(CheckType::CheckMate), Color::White) => builder.result(PGNGameTerminationMarker::BlackWins),
+        (Some(CheckType::CheckMate), Color::Black) => builder.result(PGNGameTerminationMarker::WhiteWins),
+        (Some(CheckType::StaleMate), _) => builder.result(PGNGameTerminationMarker::Draw),
+        _ => &mut builder,
+    };
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn uci_movelist_tests() {
+        let game = parse_uci_movelist("e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 f3g5 d7d5 e4d5 f6d5 g5f7 e8f7 d1f3 f7e6 b1c3 c6b4 e1g1", None).unwrap();
+        assert_eq!(game.movetext.to_string(), "1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. Ng5 d5 5. exd5 Nxd5 6. Nxf7 Kxf7 7. Qf3+ Ke6 8. Nc3 Nb4 9. O-O ");
+        assert!(game.validate().is_ok());
+
+        let game = parse_uci_movelist("f2f3 e7e5\ng2g4 d8h4", None).unwrap();
+        assert!(game.to_string().ends_with("1. f3 e5 2. g4 Qh4# 0-1"));
+
+        let position: Position = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2".parse().unwrap();
+        let game = parse_uci_movelist("b8c6 f1b5", Some(position)).unwrap();
+        assert_eq!(game.movetext.to_string(), "2... Nc6 3. Bb5 ");
+
+        let err = parse_uci_movelist("e2e4 e7e5\ne1e2 e8e7 e2e4", None).unwrap_err();
+        assert_eq!(err.to_string(), "no legal move matches 'e2e4' at 2:11");
+    }
+
+}
@@ -0,0 +1,181 @@
+use crate::definitions::*;
+use crate::definitions::bitboard::*;
+use crate::pgn::Castle;
+
+pub mod fen;
+pub mod movegen;
+pub mod perft;
+pub mod uci;
+pub mod zobrist;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+pub(crate) fn back_rank(color: Color) -> Rank {
+    Rank::R1.relative_to(color)
+}
+
+// The file of the rook each player may still castle with on each side, whether or not castling
+// is currently possible
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct CastlingRights {
+    pub(crate) white_king_side: Option<File>,
+    pub(crate) white_queen_side: Option<File>,
+    pub(crate) black_king_side: Option<File>,
+    pub(crate) black_queen_side: Option<File>,
+}
+
+impl CastlingRights {
+    pub fn king_side(&self, color: Color) -> bool {
+        self.rook_file(color, Castle::KingSide).is_some()
+    }
+
+    pub fn queen_side(&self, color: Color) -> bool {
+        self.rook_file(color, Castle::QueenSide).is_some()
+    }
+
+    pub fn rook_file(&self, color: Color, castle: Castle) -> Option<File> {
+        match (color, castle) {
+            (Color::White, Castle::KingSide) => self.white_king_side,
+            (Color::White, Castle::QueenSide) => self.white_queen_side,
+            (Color::Black, Castle::KingSide) => self.black_king_side,
+            (Color::Black, Castle::QueenSide) => self.black_queen_side,
+        }
+    }
+
+    pub(crate) fn rook_file_mut(&mut self, color: Color, castle: Castle) -> &mut Option<File> {
+        match (color, castle) {
+            (Color::White, Castle::KingSide) => &mut self.white_king_side,
+            (Color::White, Castle::QueenSide) => &mut self.white_queen_side,
+            (Color::Black, Castle::KingSide) => &mut self.black_king_side,
+            (Color::Black, Castle::QueenSide) => &mut self.black_queen_side,
+        }
+    }
+}
+
+// A position as described by FEN, the board is indexed by square reference from a1 to h8. The
+// same pieces are held as bitboards by colour and by role for move generation, kept in step
+// with the board by put and remove.
+// Chess960 positions are those castling with a king or rook away from its standard square, or
+// from a game whose Variant tag says so.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub(crate) board: [Option<Piece>; BOARD_SIZE],
+    pub(crate) by_color: [Bitboard; 2],
+    pub(crate) by_role: [Bitboard; COUNT_ROLES],
+    pub(crate) side_to_move: Color,
+    pub(crate) castling_rights: CastlingRights,
+    pub(crate) en_passant: Option<Square>,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
+    pub(crate) chess960: bool,
+}
+
+impl Position {
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.board[square.index()]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.by_color[Color::White as usize] | self.by_color[Color::Black as usize]
+    }
+
+    pub fn by_color(&self, color: Color) -> Bitboard {
+        self.by_color[color as usize]
+    }
+
+    pub fn by_role(&self, role: Role) -> Bitboard {
+        self.by_role[role as usize]
+    }
+
+    pub fn pieces(&self, color: Color, role: Role) -> Bitboard {
+        self.by_color(color) & self.by_role(role)
+    }
+
+    // Places a piece on a square, replacing whatever stood there
+    pub(crate) fn put(&mut self, square: Square, piece: Piece) {
+        self.remove(square);
+        self.board[square.index()] = Some(piece);
+        self.by_color[piece.color as usize] |= Bitboard::from_square(square);
+        self.by_role[piece.role as usize] |= Bitboard::from_square(square);
+    }
+
+    pub(crate) fn remove(&mut self, square: Square) {
+        if let Some(piece) = self.board[square.index()].take() {
+            self.by_color[piece.color as usize] &= !Bitboard::from_square(square);
+            self.by_role[piece.role as usize] &= !Bitboard::from_square(square);
+        }
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    pub fn en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    // Whether neither side could checkmate by any series of moves because too little material
+    // is left: kings alone, a king and single minor piece against a king, or only bishops that
+    // all stand on squares of the same colour
+    pub fn is_insufficient_material(&self) -> bool {
+        let pieces: Vec<(Square, Role)> = Square::all().zip(self.board)
+            .filter_map(| (square, piece) | piece.map(| piece | (square, piece.role)))
+            .filter(| (_, role) | *role != Role::King)
+            .collect();
+        match pieces.as_slice() {
+            [] | [(_, Role::Knight | Role::Bishop)] => true,
+            [(first, _), ..] => pieces.iter().all(| (square, role) | *role == Role::Bishop && square.is_light() == first.is_light()),
+        }
+    }
+
+    // The same position played under Chess960 rules, which only changes how castling is
+    // written in UCI
+    pub fn into_chess960(mut self) -> Position {
+        self.chess960 = true;
+        self
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        STARTING_FEN.parse().expect("starting position FEN is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn insufficient_material_tests() {
+        for (fen, insufficient) in [
+            ("8/8/4k3/8/8/3K4/8/8 w - - 0 1", true),
+            ("8/8/4k3/8/8/3KN3/8/8 w - - 0 1", true),
+            ("8/8/4k3/2b5/8/3KB3/8/8 w - - 0 1", true),
+            ("8/3bk3/8/8/8/3KB3/8/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/3KNN2/8/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/3K4/7P/8 w - - 0 1", false),
+            (STARTING_FEN, false),
+        ] {
+            assert_eq!(fen.parse::<Position>().unwrap().is_insufficient_material(), insufficient, "{}", fen);
+        }
+    }
+
+}
@@ -0,0 +1,287 @@
+use crate::definitions::*;
+use crate::error::*;
+use crate::pgn::Castle;
+
+use super::*;
+
+use std::fmt;
+use std::str::FromStr;
+
+// White's pieces are upper case and black's lower case
+const FEN_ROLES: [(char, Role); 6] = [('k', Role::King), ('q', Role::Queen), ('r', Role::Rook), ('b', Role::Bishop), ('n', Role::Knight), ('p', Role::Pawn)];
+
+pub fn piece_from_fen_char(c: char) -> Option<Piece> {
+    let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+    FEN_ROLES.iter().find(| (fen_char, _) | *fen_char == c.to_ascii_lowercase()).map(| &(_, role) | Piece::new(color, role))
+}
+
+pub fn fen_char(piece: Piece) -> char {
+    let c = FEN_ROLES.iter().find(| (_, role) | *role == piece.role).map(| &(c, _) | c).unwrap_or('?');
+    if piece.color == Color::White { c.to_ascii_uppercase() } else { c }
+}
+
+fn parse_placement(fen: &str, placement: &str) -> Result<[Option<Piece>; BOARD_SIZE], FenError> {
+    let mut board = [None; BOARD_SIZE];
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 { return Err(FenError::new(FenErrorKind::RankCount, offset_in(fen, placement))); }
+
+    // Ranks are given from the eighth down to the first
+    for (rank, rank_text) in (0..8).rev().zip(ranks) {
+        let rank_length_error = | offset | FenError::new(FenErrorKind::RankLength(RANKS[rank]), offset);
+        let mut file = 0usize;
+        for (index, c) in rank_text.char_indices() {
+            let offset = offset_in(fen, rank_text) + index;
+            if file >= 8 { return Err(rank_length_error(offset)); }
+            match c.to_digit(10) {
+                Some(empty @ 1..=8) => file += empty as usize,
+                _ => {
+                    board[Square::new(FILES[file], RANKS[rank]).index()] = Some(piece_from_fen_char(c).ok_or(FenError::new(FenErrorKind::InvalidPiece(c), offset))?);
+                    file += 1;
+                },
+            }
+            if file > 8 { return Err(rank_length_error(offset)); }
+        }
+        if file != 8 { return Err(rank_length_error(offset_in(fen, rank_text) + rank_text.len())); }
+    }
+    Ok(board)
+}
+
+impl Position {
+    // The outermost rook on the back rank on the side of the king castled towards
+    fn outermost_rook_file(&self, color: Color, castle: Castle) -> Option<File> {
+        let rank = back_rank(color);
+        let piece_on_file = | file: File, role | self.board[Square::new(file, rank).index()] == Some(Piece::new(color, role));
+        let king_file = FILES.into_iter().find(| &file | piece_on_file(file, Role::King))?;
+        match castle {
+            Castle::KingSide => FILES.into_iter().rev().take_while(| &file | file > king_file).find(| &file | piece_on_file(file, Role::Rook)),
+            Castle::QueenSide => FILES.into_iter().take_while(| &file | file < king_file).find(| &file | piece_on_file(file, Role::Rook)),
+        }
+    }
+
+    // Castling rights are given as KQkq for the outermost rook on each side, or as the file of
+    // the rook as in X-FEN and Shredder-FEN, e.g. HAha. Each side may be given only once.
+    fn parse_castling_rights(&self, fen: &str, field: &str) -> Result<CastlingRights, FenError> {
+        let mut castling_rights = CastlingRights::default();
+        if field == "-" { return Ok(castling_rights); }
+        for (index, c) in field.char_indices() {
+            let offset = offset_in(fen, field) + index;
+            let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+            let rank = back_rank(color);
+            let (castle, rook_file) = match (c.to_ascii_lowercase(), File::from_char(c.to_ascii_lowercase())) {
+                ('k', _) => (Castle::KingSide, self.outermost_rook_file(color, Castle::KingSide)),
+                ('q', _) => (Castle::QueenSide, self.outermost_rook_file(color, Castle::QueenSide)),
+                (_, Some(file)) => {
+                    let king_file = self.king_square(color).filter(| king | king.rank() == rank).map(Square::file);
+                    let is_rook = self.board[Square::new(file, rank).index()] == Some(Piece::new(color, Role::Rook));
+                    match king_file {
+                        Some(king_file) if is_rook && file > king_file => (Castle::KingSide, Some(file)),
+                        Some(king_file) if is_rook && file < king_file => (Castle::QueenSide, Some(file)),
+                        _ => (Castle::KingSide, None),
+                    }
+                },
+                _ => return Err(FenError::new(FenErrorKind::InvalidCastlingRights, offset_in(fen, field))),
+            };
+            let rook_file = rook_file.ok_or(FenError::new(FenErrorKind::CastlingWithoutRook, offset))?;
+            let right = castling_rights.rook_file_mut(color, castle);
+            if right.is_some() { return Err(FenError::new(FenErrorKind::InvalidCastlingRights, offset_in(fen, field))); }
+            *right = Some(rook_file);
+        }
+        Ok(castling_rights)
+    }
+
+    // Chess960 unless every castling right is for a king on the e-file and a rook in the corner
+    fn castles_as_chess960(&self) -> bool {
+        [Color::White, Color::Black].into_iter().any(| color | {
+            let king_on_e_file = self.king_square(color).is_some_and(| king | king.file() == File::E);
+            [(Castle::KingSide, File::H), (Castle::QueenSide, File::A)].into_iter().any(| (castle, corner) | {
+                self.castling_rights.rook_file(color, castle).is_some_and(| file | !king_on_e_file || file != corner)
+            })
+        })
+    }
+
+    // An en passant square must lie directly behind a pawn of the side that just moved, with
+    // the square it moved from empty
+    fn en_passant_is_valid(&self, en_passant: Square) -> bool {
+        let (expected_rank, pawn_rank, from_rank) = match self.side_to_move {
+            Color::White => (Rank::R6, Rank::R5, Rank::R7),
+            Color::Black => (Rank::R3, Rank::R4, Rank::R2),
+        };
+        let pawn = Piece::new(self.side_to_move.opposite(), Role::Pawn);
+        en_passant.rank() == expected_rank
+            && self.board[en_passant.index()].is_none()
+            && self.board[Square::new(en_passant.file(), from_rank).index()].is_none()
+            && self.board[Square::new(en_passant.file(), pawn_rank).index()] == Some(pawn)
+    }
+
+    // Checks that the position could arise in a game
+    fn validate(&self, fen: &str, placement: &str) -> Result<(), FenError> {
+        for color in [Color::White, Color::Black] {
+            if self.board.iter().filter(| &&piece | piece == Some(Piece::new(color, Role::King))).count() != 1 {
+                return Err(FenError::new(FenErrorKind::KingCount(color), offset_in(fen, placement)));
+            }
+        }
+        let pawn_on_back_rank = Square::all().zip(self.board).any(| (square, piece) | {
+            piece.is_some_and(| piece | piece.role == Role::Pawn) && matches!(square.rank(), Rank::R1 | Rank::R8)
+        });
+        if pawn_on_back_rank { return Err(FenError::new(FenErrorKind::PawnOnBackRank, offset_in(fen, placement))); }
+        let opponent = self.side_to_move.opposite();
+        let opponent_in_check = self.king_square(opponent).is_some_and(| king | !self.attackers(king, self.side_to_move).is_empty());
+        if opponent_in_check { return Err(FenError::new(FenErrorKind::OpponentInCheck, offset_in(fen, placement))); }
+        Ok(())
+    }
+}
+
+impl FromStr for Position {
+    type Err = FenError;
+
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        let mut fields = fen.split_ascii_whitespace();
+        let mut next_field = | name | fields.next().ok_or(FenError::new(FenErrorKind::MissingField(name), fen.len()));
+        let placement = next_field("piece placement")?;
+        let side_to_move = next_field("side to move")?;
+        let castling_rights = next_field("castling rights")?;
+        let en_passant = next_field("en passant")?;
+        let halfmove_clock = next_field("halfmove clock")?;
+        let fullmove_number = next_field("fullmove number")?;
+        if let Some(field) = fields.next() { return Err(FenError::new(FenErrorKind::UnexpectedField, offset_in(fen, field))); }
+
+        let field_error = | kind, field | FenError::new(kind, offset_in(fen, field));
+        let board = parse_placement(fen, placement)?;
+        let mut position = Position {
+            board: [None; BOARD_SIZE],
+            by_color: [Bitboard::EMPTY; 2],
+            by_role: [Bitboard::EMPTY; COUNT_ROLES],
+            side_to_move: match side_to_move {
+                "w" => Color::White,
+                "b" => Color::Black,
+                _ => return Err(field_error(FenErrorKind::InvalidSideToMove, side_to_move)),
+            },
+            castling_rights: CastlingRights::default(),
+            en_passant: None,
+            halfmove_clock: halfmove_clock.parse().map_err(| _ | field_error(FenErrorKind::InvalidHalfmoveClock, halfmove_clock))?,
+            fullmove_number: match fullmove_number.parse() {
+                Ok(number) if number > 0 => number,
+                _ => return Err(field_error(FenErrorKind::InvalidFullmoveNumber, fullmove_number)),
+            },
+            chess960: false,
+        };
+        for (square, piece) in Square::all().zip(board) {
+            if let Some(piece) = piece { position.put(square, piece); }
+        }
+        position.validate(fen, placement)?;
+        position.castling_rights = position.parse_castling_rights(fen, castling_rights)?;
+        position.chess960 = position.castles_as_chess960();
+
+        if en_passant != "-" {
+            match en_passant.parse::<Square>().ok() {
+                Some(square) if position.en_passant_is_valid(square) => position.en_passant = Some(square),
+                _ => return Err(field_error(FenErrorKind::InvalidEnPassant, en_passant)),
+            }
+        }
+        Ok(position)
+    }
+}
+
+impl Position {
+    // Castling rights are written as KQkq where the rook is the outermost on its side, as X-FEN
+    // does, or always as the file of the rook for Shredder-FEN
+    fn write_castling_rights(&self, f: &mut impl fmt::Write, shredder: bool) -> fmt::Result {
+        if self.castling_rights == CastlingRights::default() { return write!(f, "-"); }
+        for (color, castle) in [(Color::White, Castle::KingSide), (Color::White, Castle::QueenSide), (Color::Black, Castle::KingSide), (Color::Black, Castle::QueenSide)] {
+            let Some(file) = self.castling_rights.rook_file(color, castle) else { continue };
+            let right = match castle {
+                _ if shredder || self.outermost_rook_file(color, castle) != Some(file) => file.char(),
+                Castle::KingSide => 'k',
+                Castle::QueenSide => 'q',
+            };
+            write!(f, "{}", if color == Color::White { right.to_ascii_uppercase() } else { right })?;
+        }
+        Ok(())
+    }
+
+    fn write_fen(&self, f: &mut impl fmt::Write, shredder: bool) -> fmt::Result {
+        for rank in RANKS.into_iter().rev() {
+            let mut empty = 0;
+            for file in FILES {
+                match self.board[Square::new(file, rank).index()] {
+                    Some(piece) => {
+                        if empty > 0 { write!(f, "{}", empty)?; }
+                        empty = 0;
+                        write!(f, "{}", fen_char(piece))?;
+                    },
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 { write!(f, "{}", empty)?; }
+            if rank != Rank::R1 { write!(f, "/")?; }
+        }
+        let side_to_move = match self.side_to_move {
+            Color::White => 'w',
+            Color::Black => 'b',
+        };
+        write!(f, " {} ", side_to_move)?;
+        self.write_castling_rights(f, shredder)?;
+        match self.en_passant {
+            Some(square) => write!(f, " {}", square)?,
+            None => write!(f, " -")?,
+        };
+        write!(f, " {} {}", self.halfmove_clock, self.fullmove_number)
+    }
+
+    // The position as FEN with the castling rights given by rook file
+    pub fn shredder_fen(&self) -> String {
+        let mut fen = String::new();
+        self.write_fen(&mut fen, true).expect("writing to a String does not fail");
+        fen
+    }
+}
+
+// Writes the position as FEN, using X-FEN castling rights where they are ambiguous
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_fen(f, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn fen_round_trip_tests() {
+        for fen in [
+            STARTING_FEN,
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w KQkq - 0 1",
+            "8/8/4k3/8/2pP4/8/8/4K3 b - d3 0 57",
+            "4k3/8/8/8/8/8/8/R3KR1R w FQ - 0 1",
+        ] {
+            assert_eq!(fen.parse::<Position>().unwrap().to_string(), fen);
+        }
+        let shredder: Position = "rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w HAha - 0 1".parse().unwrap();
+        assert_eq!(shredder.to_string(), "rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w KQkq - 0 1");
+        assert_eq!(shredder.shredder_fen(), "rbbkqnnr/pppppppp/8/8/8/8/PPPPPPPP/RBBKQNNR w HAha - 0 1");
+        assert!(shredder.is_chess960());
+        assert!(!Position::default().is_chess960());
+        let position = Position::default();
+        assert_eq!(position.piece_at(Square::new(File::E, Rank::R1)), Some(Piece::new(Color::White, Role::King)));
+        assert_eq!(position.side_to_move(), Color::White);
+        assert!(position.castling_rights().queen_side(Color::Black));
+    }
+
+    #[test]
+    fn fen_error_tests() {
+        let error = | fen: &str | fen.parse::<Position>().unwrap_err().to_string();
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1"), "piece placement does not have eight ranks at character 1");
+        assert_eq!(error("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), "rank 7 does not have eight squares at character 18");
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBXR w KQkq - 0 1"), "'X' is neither a piece nor a count of empty squares at character 42");
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1"), "castling right without a king and rook on the back rank at character 47");
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KHkq - 0 1"), "castling rights are not '-' or a combination of 'KQkq' or rook files at character 47");
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w EQkq - 0 1"), "castling right without a king and rook on the back rank at character 47");
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e4 0 1"), "en passant square is not behind a pawn that has just moved two squares at character 54");
+        assert_eq!(error("4k3/8/8/8/8/8/8/4RK2 w - - 0 1"), "the side not to move is in check at character 1");
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0"), "missing the fullmove number field at character 55");
+    }
+
+}
@@ -0,0 +1,356 @@
+use crate::definitions::attacks::*;
+use crate::error::*;
+use crate::pgn::*;
+
+use super::*;
+
+const PROMOTION_ROLES: [Role; 4] = [Role::Queen, Role::Rook, Role::Bishop, Role::Knight];
+
+// A concrete move. Castling is given as the king's move, the rook's move following from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub(crate) from: Square,
+    pub(crate) to: Square,
+    pub(crate) piece: Role,
+    pub(crate) captured: Option<Role>,
+    pub(crate) promotion: Option<Role>,
+    pub(crate) castle: Option<Castle>,
+    pub(crate) en_passant: bool,
+}
+
+impl Move {
+    pub fn from(&self) -> Square {
+        self.from
+    }
+
+    pub fn to(&self) -> Square {
+        self.to
+    }
+
+    pub fn piece(&self) -> Role {
+        self.piece
+    }
+
+    pub fn captured(&self) -> Option<Role> {
+        self.captured
+    }
+
+    pub fn promotion(&self) -> Option<Role> {
+        self.promotion
+    }
+
+    pub fn castle(&self) -> Option<Castle> {
+        self.castle
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.en_passant
+    }
+}
+
+fn pawn_direction(color: Color) -> i32 {
+    match color {
+        Color::White => 1,
+        Color::Black => -1,
+    }
+}
+
+impl Position {
+    // The rook a king castles with, on the file given by the castling rights
+    pub(crate) fn castling_rook(&self, color: Color, castle: Castle) -> Option<Square> {
+        let file = self.castling_rights.rook_file(color, castle)?;
+        let rook = Square::new(file, back_rank(color));
+        (self.board[rook.index()] == Some(Piece::new(color, Role::Rook))).then_some(rook)
+    }
+
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        self.pieces(color, Role::King).first()
+    }
+
+    // The pieces of the colour given attacking the square. A pawn attacks the square from where
+    // a pawn of the other colour standing on it would capture.
+    pub fn attackers(&self, square: Square, by: Color) -> Bitboard {
+        let occupied = self.occupied();
+        let rooks_and_queens = self.by_role(Role::Rook) | self.by_role(Role::Queen);
+        let bishops_and_queens = self.by_role(Role::Bishop) | self.by_role(Role::Queen);
+        let attackers = (pawn_attacks(by.opposite(), square) & self.by_role(Role::Pawn))
+            | (knight_attacks(square) & self.by_role(Role::Knight))
+            | (king_attacks(square) & self.by_role(Role::King))
+            | (rook_attacks(square, occupied) & rooks_and_queens)
+            | (bishop_attacks(square, occupied) & bishops_and_queens);
+        attackers & self.by_color(by)
+    }
+
+    pub fn is_attacked(&self, square: Square, by: Color) -> bool {
+        !self.attackers(square, by).is_empty()
+    }
+
+    pub fn is_check(&self) -> bool {
+        self.king_square(self.side_to_move).is_some_and(| king | self.is_attacked(king, self.side_to_move.opposite()))
+    }
+
+    fn pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let color = self.side_to_move;
+        let direction = pawn_direction(color);
+        let start_rank = Rank::R2.relative_to(color);
+        let mut add = | to: Square, captured, en_passant | {
+            let base = Move { from, to, piece: Role::Pawn, captured, promotion: None, castle: None, en_passant };
+            if to.rank() == back_rank(color.opposite()) {
+                moves.extend(PROMOTION_ROLES.iter().map(| &promotion | Move { promotion: Some(promotion), ..base }));
+            } else {
+                moves.push(base);
+            }
+        };
+
+        if let Some(to) = from.offset(0, direction).filter(| to | self.board[to.index()].is_none()) {
+            add(to, None, false);
+            if from.rank() == start_rank {
+                if let Some(to) = to.offset(0, direction).filter(| to | self.board[to.index()].is_none()) {
+                    add(to, None, false);
+                }
+            }
+        }
+        for to in pawn_attacks(color, from) {
+            match self.board[to.index()] {
+                Some(target) if target.color != color => add(to, Some(target.role), false),
+                None if self.en_passant == Some(to) => add(to, Some(Role::Pawn), true),
+                _ => (),
+            }
+        }
+    }
+
+    fn piece_moves(&self, from: Square, piece: Role, moves: &mut Vec<Move>) {
+        let occupied = self.occupied();
+        let attacks = match piece {
+            Role::Knight => knight_attacks(from),
+            Role::King => king_attacks(from),
+            Role::Bishop => bishop_attacks(from, occupied),
+            Role::Rook => rook_attacks(from, occupied),
+            Role::Queen => queen_attacks(from, occupied),
+            Role::Pawn => Bitboard::EMPTY,
+        };
+        for to in attacks & !self.by_color(self.side_to_move) {
+            let captured = self.board[to.index()].map(| target | target.role);
+            moves.push(Move { from, to, piece, captured, promotion: None, castle: None, en_passant: false });
+        }
+    }
+
+    // The king and rook end on the g and f files, or c and d, wherever they start from. Squares
+    // between their start and end must be empty of other pieces, and the king may not castle out
+    // of, through or into check.
+    fn castling_moves(&self, moves: &mut Vec<Move>) {
+        let color = self.side_to_move;
+        let rank = back_rank(color);
+        for (castle, king_file, rook_file) in [(Castle::KingSide, File::G, File::F), (Castle::QueenSide, File::C, File::D)] {
+            let (Some(king), Some(rook)) = (self.king_square(color), self.castling_rook(color, castle)) else { continue };
+            if king.rank() != rank { continue; }
+            let (king_to, rook_to) = (Square::new(king_file, rank), Square::new(rook_file, rank));
+
+            let squares = [king, king_to, rook, rook_to];
+            let span = *squares.iter().min().unwrap()..=*squares.iter().max().unwrap();
+            let blocked = Square::all().filter(| square | span.contains(square)).any(| square | square != king && square != rook && self.board[square.index()].is_some());
+            let king_path = king.min(king_to)..=king.max(king_to);
+            let attacked = Square::all().filter(| square | king_path.contains(square)).any(| square | self.is_attacked(square, color.opposite()));
+            if !blocked && !attacked {
+                moves.push(Move { from: king, to: king_to, piece: Role::King, captured: None, promotion: None, castle: Some(castle), en_passant: false });
+            }
+        }
+    }
+
+    // Moves obeying how each piece moves, which may leave the king in check
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for from in self.by_color(self.side_to_move) {
+            match self.board[from.index()] {
+                Some(Piece { role: Role::Pawn, .. }) => self.pawn_moves(from, &mut moves),
+                Some(piece) => self.piece_moves(from, piece.role, &mut moves),
+                None => (),
+            }
+        }
+        self.castling_moves(&mut moves);
+        moves
+    }
+
+    // Whether the side to move is in check, checkmated or stalemated
+    pub fn check_type(&self) -> Option<CheckType> {
+        match (self.is_check(), self.legal_moves().is_empty()) {
+            (true, true) => Some(CheckType::CheckMate),
+            (true, false) => Some(CheckType::Check),
+            (false, true) => Some(CheckType::StaleMate),
+            (false, false) => None,
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let color = self.side_to_move;
+        self.pseudo_legal_moves().into_iter().filter(| mv | {
+            let position = self.play(mv);
+            position.king_square(color).is_some_and(| king | !position.is_attacked(king, color.opposite()))
+        }).collect()
+    }
+
+    // Returns the position after the move, which must be one of the legal moves of this position
+    pub fn play(&self, mv: &Move) -> Position {
+        let color = self.side_to_move;
+        let mut position = self.clone();
+
+        match mv.castle {
+            Some(castle) => {
+                let rook = self.castling_rook(color, castle).expect("castling move has a rook");
+                let rook_to = Square::new(if castle == Castle::KingSide { File::F } else { File::D }, back_rank(color));
+                position.remove(mv.from);
+                position.remove(rook);
+                position.put(mv.to, Piece::new(color, Role::King));
+                position.put(rook_to, Piece::new(color, Role::Rook));
+            },
+            None => {
+                position.remove(mv.from);
+                position.put(mv.to, Piece::new(color, mv.promotion.unwrap_or(mv.piece)));
+                if mv.en_passant {
+                    let captured = mv.to.offset(0, -pawn_direction(color)).expect("en passant capture is on the board");
+                    position.remove(captured);
+                }
+            },
+        }
+
+        // Castling rights are lost once the king or castling rook moves, or the rook is captured
+        for side in [Color::White, Color::Black] {
+            let king_moved = side == color && mv.piece == Role::King;
+            for castle in [Castle::KingSide, Castle::QueenSide] {
+                let rook_moved = self.castling_rook(side, castle).is_some_and(| rook | rook == mv.from || rook == mv.to);
+                if king_moved || rook_moved { *position.castling_rights.rook_file_mut(side, castle) = None; }
+            }
+        }
+
+        let double_push = mv.piece == Role::Pawn && mv.from.rank().distance(mv.to.rank()) == 2;
+        position.en_passant = if double_push { mv.from.offset(0, pawn_direction(color)) } else { None };
+        position.halfmove_clock = if mv.piece == Role::Pawn || mv.captured.is_some() { 0 } else { self.halfmove_clock + 1 };
+        if color == Color::Black { position.fullmove_number += 1; }
+        position.side_to_move = color.opposite();
+        position
+    }
+
+    // Finds the legal move described by the SAN, which must match exactly one move. Captures
+    // must be marked as such and a pawn reaching the last rank must name its promotion.
+    pub fn resolve_san(&self, san: &SANply) -> Result<Move, SanError> {
+        let matches = | mv: &Move | match san {
+            SANply::Castle(castle) => mv.castle == Some(*castle),
+            SANply::Basic { piece_moved, mv: coordinates } => mv.piece == *piece_moved && mv.captured.is_none() && mv.promotion.is_none() && coordinates_match(coordinates, mv),
+            SANply::Capture { piece_moved, mv: coordinates } => mv.piece == *piece_moved && mv.captured.is_some() && mv.promotion.is_none() && coordinates_match(coordinates, mv),
+            SANply::Promotion { mv: coordinates, piece_promoted } => mv.captured.is_none() && mv.promotion == Some(*piece_promoted) && coordinates_match(coordinates, mv),
+            SANply::CapturePromotion { mv: coordinates, piece_promoted } => mv.captured.is_some() && mv.promotion == Some(*piece_promoted) && coordinates_match(coordinates, mv),
+        };
+        // Castling is only ever written as O-O or O-O-O, never as the king's move
+        let mut candidates = self.legal_moves().into_iter().filter(| mv | mv.castle.is_none() || matches!(san, SANply::Castle(_))).filter(matches);
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Ok(mv),
+            (Some(_), Some(_)) => Err(SanError::Ambiguous(san.to_string())),
+            (None, _) => Err(SanError::NoLegalMove(san.to_string())),
+        }
+    }
+
+    // Writes a legal move as canonical SAN. A piece move is qualified by its from file if that
+    // tells it apart from the other pieces of its kind reaching the same square, otherwise by its
+    // from rank, and by both only when neither does.
+    pub fn san(&self, mv: &Move) -> SANply {
+        if let Some(castle) = mv.castle { return SANply::Castle(castle); }
+        let (from_file, from_rank) = (mv.from.file(), mv.from.rank());
+        let mut coordinates = SANPlyCoordinates { from_file: None, from_rank: None, to_square: mv.to };
+
+        if mv.piece == Role::Pawn {
+            if mv.captured.is_some() { coordinates.from_file = Some(from_file); }
+            return match (mv.captured, mv.promotion) {
+                (None, None) => SANply::Basic { piece_moved: mv.piece, mv: coordinates },
+                (Some(_), None) => SANply::Capture { piece_moved: mv.piece, mv: coordinates },
+                (None, Some(piece_promoted)) => SANply::Promotion { mv: coordinates, piece_promoted },
+                (Some(_), Some(piece_promoted)) => SANply::CapturePromotion { mv: coordinates, piece_promoted },
+            };
+        }
+
+        let rivals: Vec<Square> = self.legal_moves().into_iter()
+            .filter(| other | other.piece == mv.piece && other.to == mv.to && other.from != mv.from && other.castle.is_none())
+            .map(| other | other.from)
+            .collect();
+        if !rivals.is_empty() {
+            if rivals.iter().all(| rival | rival.file() != from_file) {
+                coordinates.from_file = Some(from_file);
+            } else if rivals.iter().all(| rival | rival.rank() != from_rank) {
+                coordinates.from_rank = Some(from_rank);
+            } else {
+                coordinates.from_file = Some(from_file);
+                coordinates.from_rank = Some(from_rank);
+            }
+        }
+        match mv.captured {
+            Some(_) => SANply::Capture { piece_moved: mv.piece, mv: coordinates },
+            None => SANply::Basic { piece_moved: mv.piece, mv: coordinates },
+        }
+    }
+}
+
+fn coordinates_match(coordinates: &SANPlyCoordinates, mv: &Move) -> bool {
+    mv.to == coordinates.to_square
+        && coordinates.from_file.is_none_or(| file | mv.from.file() == file)
+        && coordinates.from_rank.is_none_or(| rank | mv.from.rank() == rank)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::pgn::pgn_import::*;
+
+    fn resolve(fen: &str, san: &str) -> Result<Move, SanError> {
+        let position: Position = fen.parse().unwrap();
+        let (_, (san, _)) = parse_san_ply::<nom::error::Error<_>>(san).unwrap();
+        position.resolve_san(&san)
+    }
+
+    #[test]
+    fn legal_move_tests() {
+        assert_eq!(Position::default().legal_moves().len(), 20);
+        let kiwipete: Position = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".parse().unwrap();
+        assert_eq!(kiwipete.legal_moves().len(), 48);
+        let checkmate: Position = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".parse().unwrap();
+        assert!(checkmate.is_check());
+        assert!(checkmate.legal_moves().is_empty());
+        assert_eq!(checkmate.check_type(), Some(CheckType::CheckMate));
+        let stalemate: Position = "k7/2Q5/8/8/8/8/8/7K b - - 1 1".parse().unwrap();
+        assert_eq!(stalemate.check_type(), Some(CheckType::StaleMate));
+    }
+
+    #[test]
+    fn resolve_san_tests() {
+        let knights = "r3k2r/pp1n1ppp/8/2pPp3/8/8/PPP2PPP/R3K1NR b KQkq - 0 12";
+        let mv = resolve(knights, "Nb6").unwrap();
+        assert_eq!((mv.from().to_string(), mv.to().to_string()), ("d7".to_string(), "b6".to_string()));
+        assert_eq!(resolve(knights, "O-O-O").unwrap().to().to_string(), "c8");
+        assert_eq!(resolve(knights, "Nf3").unwrap_err().to_string(), "no legal move matches 'Nf3'");
+
+        let rooks = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1";
+        assert_eq!(resolve(rooks, "Re1").unwrap_err().to_string(), "'Re1' matches more than one legal move");
+        assert_eq!(resolve(rooks, "Rhe1").unwrap().from().to_string(), "h1");
+        assert_eq!(resolve(rooks, "Rxe1").unwrap_err().to_string(), "no legal move matches 'Rxe1'");
+
+        let en_passant = "4k3/8/8/2pP4/8/8/8/4K3 w - c6 0 2";
+        assert!(resolve(en_passant, "dxc6").unwrap().is_en_passant());
+        assert_eq!(resolve("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8=N").unwrap().promotion(), Some(Role::Knight));
+        assert!(resolve("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8").is_err());
+    }
+
+    #[test]
+    fn san_tests() {
+        let canonical = | fen: &str, san: &str | fen.parse::<Position>().unwrap().san(&resolve(fen, san).unwrap()).to_string();
+        let rooks = "4k3/8/8/8/8/R7/4K3/R6R w - - 0 1";
+        assert_eq!(canonical(rooks, "Ra1a2"), "R1a2");
+        assert_eq!(canonical(rooks, "Ra1d1"), "Rad1");
+        assert_eq!(canonical(rooks, "Ra3d3"), "Rd3");
+        assert_eq!(canonical("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "O-O-O"), "O-O-O");
+        let queens = "k7/8/8/8/8/2Q1Q3/8/2Q1K3 w - - 0 1";
+        assert_eq!(canonical(queens, "Qc3d2"), "Qc3d2");
+        assert_eq!(canonical(queens, "Qc1d2"), "Q1d2");
+        assert_eq!(canonical(queens, "Qe3d2"), "Qed2");
+        assert_eq!(canonical("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "axb8=Q"), "axb8=Q");
+    }
+
+}
@@ -0,0 +1,102 @@
+use super::*;
+use super::movegen::*;
+
+// A position with the number of leaf nodes of its move tree at each depth from 1, which any move
+// generator can be checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerftCase {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub node_counts: &'static [u64],
+}
+
+// Well known positions between them covering castling, including through and out of check,
+// promotions, en passant captures exposing the king along a rank, and Chess960 castling
+pub const PERFT_SUITE: [PerftCase; 7] = [
+    PerftCase {
+        name: "starting position",
+        fen: STARTING_FEN,
+        node_counts: &[20, 400, 8902, 197281, 4865609, 119060324],
+    },
+    PerftCase {
+        name: "kiwipete",
+        fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        node_counts: &[48, 2039, 97862, 4085603, 193690690],
+    },
+    PerftCase {
+        name: "rook and pawns endgame",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        node_counts: &[14, 191, 2812, 43238, 674624, 11030083],
+    },
+    PerftCase {
+        name: "promotions and castling rights",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        node_counts: &[6, 264, 9467, 422333, 15833292],
+    },
+    PerftCase {
+        name: "promotion with check",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        node_counts: &[44, 1486, 62379, 2103487, 89941194],
+    },
+    PerftCase {
+        name: "middlegame",
+        fen: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        node_counts: &[46, 2079, 89890, 3894594, 164075551],
+    },
+    PerftCase {
+        name: "chess960",
+        fen: "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        node_counts: &[21, 528, 12189, 326672, 8146062],
+    },
+];
+
+// The number of leaf nodes of the tree of legal moves to the given depth
+pub fn perft(position: &Position, depth: u32) -> u64 {
+    let moves = position.legal_moves();
+    match depth {
+        0 => 1,
+        1 => moves.len() as u64,
+        _ => moves.iter().map(| mv | perft(&position.play(mv), depth - 1)).sum(),
+    }
+}
+
+// The perft count below each legal move, for finding which move a generator goes wrong on
+pub fn divide(position: &Position, depth: u32) -> Vec<(Move, u64)> {
+    position.legal_moves().into_iter().map(| mv | (mv, perft(&position.play(&mv), depth.saturating_sub(1)))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn perft_suite_test_to(max_nodes: u64) {
+        for case in PERFT_SUITE {
+            let position: Position = case.fen.parse().unwrap();
+            for (depth, &node_count) in (1..).zip(case.node_counts).take_while(| (_, &node_count) | node_count <= max_nodes) {
+                assert_eq!(perft(&position, depth), node_count, "{} at depth {}", case.name, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn perft_suite_test() {
+        // Deep enough to reach en passant with a discovered check, castling through attacked
+        // squares and underpromotion, deeper counts take too long for an unoptimised build
+        perft_suite_test_to(500_000);
+
+        let position = Position::default();
+        let divided = divide(&position, 2);
+        assert_eq!(divided.len(), 20);
+        assert!(divided.iter().all(| (_, nodes) | *nodes == 20));
+        assert_eq!(divided.iter().find(| (mv, _) | position.uci(mv) == "g1f3").map(| (_, nodes) | *nodes), Some(20));
+    }
+
+    // Every published depth, several hundred million nodes, best run with --release
+    #[test]
+    #[ignore]
+    fn perft_suite_full_test() {
+        perft_suite_test_to(u64::MAX);
+    }
+
+}
@@ -0,0 +1,78 @@
+use crate::error::*;
+use crate::pgn::Castle;
+
+use super::*;
+use super::fen::*;
+use super::movegen::*;
+
+impl Position {
+    // Writes a legal move in UCI long algebraic notation, e.g. e2e4 or e7e8q. Castling is written
+    // as the king's move, or in Chess960 as the king taking its own rook as engines expect there.
+    pub fn uci(&self, mv: &Move) -> String {
+        let to = match mv.castle() {
+            Some(castle) if self.chess960 => self.castling_rook(self.side_to_move, castle).expect("castling move has a rook"),
+            _ => mv.to(),
+        };
+        match mv.promotion() {
+            Some(piece) => format!("{}{}{}", mv.from(), to, fen_char(Piece::new(Color::Black, piece))),
+            None => format!("{}{}", mv.from(), to),
+        }
+    }
+
+    // Finds the legal move described in UCI notation. Castling may be given as either the king's
+    // move or the king taking its own rook, a plain king move being preferred should both match.
+    pub fn resolve_uci(&self, uci: &str) -> Result<Move, UciError> {
+        let syntax_error = || UciError::Syntax(uci.to_string());
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) { return Err(syntax_error()); }
+        let from: Square = uci[0..2].parse().map_err(| _ | syntax_error())?;
+        let to: Square = uci[2..4].parse().map_err(| _ | syntax_error())?;
+        let promotion = match uci[4..].chars().next() {
+            Some(c) => match piece_from_fen_char(c) {
+                Some(Piece { color: Color::Black, role }) => Some(role),
+                _ => return Err(syntax_error()),
+            },
+            None => None,
+        };
+
+        let legal_moves = self.legal_moves();
+        let plain = legal_moves.iter().find(| mv | mv.castle().is_none() && mv.from() == from && mv.to() == to && mv.promotion() == promotion);
+        let castle = legal_moves.iter().find(| mv | {
+            let rook = mv.castle().and_then(| castle: Castle | self.castling_rook(self.side_to_move, castle));
+            mv.castle().is_some() && promotion.is_none() && mv.from() == from && (mv.to() == to || rook == Some(to))
+        });
+        plain.or(castle).copied().ok_or_else(|| UciError::NoLegalMove(uci.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn uci_tests() {
+        let position = Position::default();
+        let mv = position.resolve_uci("g1f3").unwrap();
+        assert_eq!(position.uci(&mv), "g1f3");
+        assert_eq!(position.resolve_uci("e2e5").unwrap_err().to_string(), "no legal move matches 'e2e5'");
+        assert_eq!(position.resolve_uci("e2e4x").unwrap_err().to_string(), "'e2e4x' is not a UCI move");
+
+        let castling: Position = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".parse().unwrap();
+        for uci in ["e1g1", "e1h1"] {
+            let mv = castling.resolve_uci(uci).unwrap();
+            assert_eq!((mv.castle(), castling.uci(&mv)), (Some(Castle::KingSide), "e1g1".to_string()));
+        }
+
+        // The king on f1 can step to g1 or castle there, castling is written as taking the rook
+        let chess960: Position = "3k4/8/8/8/8/8/8/4RK1R w K - 0 1".parse().unwrap();
+        assert_eq!(chess960.resolve_uci("f1g1").unwrap().castle(), None);
+        let mv = chess960.resolve_uci("f1h1").unwrap();
+        assert_eq!((mv.castle(), chess960.uci(&mv)), (Some(Castle::KingSide), "f1h1".to_string()));
+
+        let promotion: Position = "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1".parse().unwrap();
+        let mv = promotion.resolve_uci("b7b8n").unwrap();
+        assert_eq!((mv.promotion(), promotion.uci(&mv)), (Some(Role::Knight), "b7b8n".to_string()));
+        assert_eq!(promotion.resolve_uci("b7b8").unwrap_err().to_string(), "no legal move matches 'b7b8'");
+    }
+
+}